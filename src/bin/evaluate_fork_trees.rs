@@ -8,25 +8,22 @@ use std::{
 use disjoint::DisjointSet;
 use render_as_tree::render;
 use version_history_inference::{
-    evaluation::forks::{gen_version_name, VersionRef},
+    testing::forks::{gen_version_name, VersionRef},
     types::{DiffInfo, TreeNode},
     utils::produce_label_tree,
 };
 
 fn gen_name_map(node: &TreeNode<DiffInfo>) -> HashMap<String, usize> {
-    let mut stack = vec![node];
     let mut name_map = HashMap::new();
-    while let Some(current) = stack.pop() {
+    for (_, current) in node.iter() {
         name_map.insert(current.value.name.to_owned(), name_map.len());
-        stack.extend(&current.children);
     }
     name_map
 }
 
 fn gen_disjoint_set(node: &TreeNode<DiffInfo>, name_map: &HashMap<String, usize>) -> DisjointSet {
-    let mut stack = vec![node];
     let mut disjoint_set = DisjointSet::with_len(name_map.len());
-    while let Some(current) = stack.pop() {
+    for (_, current) in node.iter() {
         let &curr_idx = name_map.get(&current.value.name).unwrap();
         for child in &current.children {
             if child.value.no_changes() {
@@ -35,7 +32,6 @@ fn gen_disjoint_set(node: &TreeNode<DiffInfo>, name_map: &HashMap<String, usize>
                 // println!("SAME: {} == {}", &current.value.name, &child.value.name);
             }
         }
-        stack.extend(&current.children);
     }
     disjoint_set
 }
@@ -111,23 +107,16 @@ fn normalise_identical(
 }
 
 fn make_ancestor_sets(tree: &TreeNode<String>) -> HashMap<String, HashSet<String>> {
-    fn inner(
-        node: &TreeNode<String>,
-        ancestors: &HashSet<String>,
-        map: &mut HashMap<String, HashSet<String>>,
-    ) {
-        map.insert(node.value.to_owned(), ancestors.clone());
-
-        let mut including_self = ancestors.clone();
-        including_self.insert(node.value.to_owned());
-
-        for child in &node.children {
-            inner(child, &including_self, map);
-        }
-    }
-    let mut ancestor_sets = HashMap::new();
-    inner(tree, &HashSet::new(), &mut ancestor_sets);
-    ancestor_sets
+    tree.iter()
+        .map(|(_, node)| {
+            let ancestors = tree
+                .ancestors(&node.value, None)
+                .into_iter()
+                .flatten()
+                .collect();
+            (node.value.to_owned(), ancestors)
+        })
+        .collect()
 }
 
 fn compare_ancestor_sets(
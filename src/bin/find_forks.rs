@@ -11,10 +11,9 @@ use octocrab::{models::Repository, params::repos::forks::Sort, Octocrab};
 use render_as_tree::render;
 use similar::DiffableStr;
 use version_history_inference::{
-    testing::{
-        cloning::{clone_commits_drop_git, Commit},
-        forks::build_fork_tree,
-    },
+    evaluation::cloning::{clone_commits_drop_git, Commit},
+    inference::matcher::Matcher,
+    testing::forks::build_fork_tree,
     types::TreeNode,
 };
 
@@ -70,9 +69,18 @@ async fn main() {
         stack.extend(node.children);
     }
 
+    let cache_root = std::path::Path::new("temp/repo-cache");
+    let matcher = Matcher::default();
     for (repo_full_name, commit_set) in commits_hash_map.into_iter() {
         let url = format!("https://github.com/{repo_full_name}.git");
         let commits: Vec<Commit> = commit_set.into_iter().collect();
-        clone_commits_drop_git(&url, &commits, "temp/imgui-forks");
+        clone_commits_drop_git(
+            &url,
+            &commits,
+            "temp/imgui-forks",
+            cache_root,
+            false,
+            &matcher,
+        );
     }
 }
@@ -5,6 +5,7 @@ use indicatif::{HumanDuration, MultiProgress, ProgressBar};
 use render_as_tree::render;
 use std::{
     fs::{self, File},
+    io,
     path::{Path, PathBuf},
     process::exit,
     time::Duration,
@@ -12,21 +13,99 @@ use std::{
 use version_history_inference::{
     git_generation::{build_instruction_trees, gen_git_repo, GitI},
     inference::{
-        engine::infer_version_tree,
+        diffing::DEFAULT_RENAME_SIMILARITY_THRESHOLD,
+        distance_model::DefaultModel,
+        engine::infer_version_tree_cached,
         file_fetching::{load_file_versions, load_versions},
+        verify::verify_tree,
     },
     types::{DiffInfo, TreeNode},
-    utils::{produce_label_tree, InferencePerformanceTracker, PB_SPINNER_STYLE},
+    utils::{produce_dot, produce_label_tree, InferencePerformanceTracker, PB_SPINNER_STYLE},
 };
 
 #[derive(Debug)]
 enum Config {
-    /// directory, file extension, recursive, multithreading, trace_perf filename, dry_run
-    Infer(PathBuf, Option<String>, bool, bool, Option<String>, bool),
-    /// directory
-    View(PathBuf),
+    /// directory, file extension, recursive, multithreading, trace_perf filename, dry_run, weight overrides, rename threshold, format, exclude patterns
+    Infer(
+        PathBuf,
+        Option<String>,
+        bool,
+        bool,
+        Option<String>,
+        bool,
+        Vec<(String, f32)>,
+        f32,
+        String,
+        Vec<String>,
+    ),
+    /// directory, format
+    View(PathBuf, String),
     /// directory, name
     GitGen(PathBuf, String),
+    /// directory, file extension, recursive, multithreading, weight overrides, rename threshold, exclude patterns
+    Verify(
+        PathBuf,
+        Option<String>,
+        bool,
+        bool,
+        Vec<(String, f32)>,
+        f32,
+        Vec<String>,
+    ),
+    /// directory, file extension, recursive, multithreading, weight overrides, rename threshold, format, exclude patterns
+    Update(
+        PathBuf,
+        Option<String>,
+        bool,
+        bool,
+        Vec<(String, f32)>,
+        f32,
+        String,
+        Vec<String>,
+    ),
+}
+
+/// Print a version tree in the requested `--format` (`ascii`, `json`, or `dot`)
+fn print_tree(version_tree: &TreeNode<DiffInfo>, format: &str) {
+    match format {
+        "json" => {
+            serde_json::to_writer(io::stdout(), version_tree).unwrap();
+            println!();
+        }
+        "dot" => println!("{}", produce_dot(version_tree)),
+        _ => {
+            let label_tree = produce_label_tree(version_tree);
+            println!("{}", render(&label_tree).join("\n"));
+        }
+    }
+}
+
+/// Parse a `key=value` distance model weight override, e.g. `add-file=2.0`
+fn parse_weight(raw: &str) -> Result<(String, f32), String> {
+    let (key, value) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("Weight override '{raw}' must be in the form key=value"))?;
+    let value: f32 = value
+        .parse()
+        .map_err(|_| format!("Weight override '{raw}' has a non-numeric value"))?;
+    Ok((key.to_owned(), value))
+}
+
+/// Apply `--weight` overrides onto a `DefaultModel`, by field name
+fn apply_weight_overrides(model: &mut DefaultModel, overrides: &[(String, f32)]) {
+    for (key, value) in overrides {
+        match key.as_str() {
+            "add-file" => model.add_file = *value,
+            "delete-file" => model.delete_file = *value,
+            "modify-file" => model.modify_file = *value,
+            "add-line" => model.add_line = *value,
+            "delete-line" => model.delete_line = *value,
+            "line-cap" => model.line_cap = *value as usize,
+            other => {
+                eprintln!("Unknown weight '{other}', ignoring");
+            }
+        }
+    }
 }
 
 fn parse_args() -> Config {
@@ -59,6 +138,30 @@ fn parse_args() -> Config {
                 .arg(
                     arg!(-d --"dry-run" "Skip creation of version_tree.json").action(ArgAction::SetTrue)
                 )
+                .arg(
+                    arg!(-w --weight <"key=value"> "Override a distance model weight (add-file, delete-file, modify-file, add-line, delete-line, line-cap), can be repeated")
+                    .id("weight")
+                    .action(ArgAction::Append)
+                    .value_parser(value_parser!(String))
+                )
+                .arg(
+                    arg!(--"rename-threshold" <ratio> "Minimum content-similarity ratio (0.0-1.0) for a deleted/added file pair to be reported as a rename")
+                    .id("rename-threshold")
+                    .value_parser(value_parser!(f32))
+                    .default_value(DEFAULT_RENAME_SIMILARITY_THRESHOLD.to_string())
+                )
+                .arg(
+                    arg!(--format <format> "Output format for the inferred tree")
+                    .id("format")
+                    .value_parser(["ascii", "json", "dot"])
+                    .default_value("ascii")
+                )
+                .arg(
+                    arg!(-e --exclude <pattern> "Glob or regex: pattern to exclude from the scan (also read from .vhiignore), can be repeated")
+                    .id("exclude")
+                    .action(ArgAction::Append)
+                    .value_parser(value_parser!(String))
+                )
         )
         .subcommand(
             Command::new("view")
@@ -68,6 +171,12 @@ fn parse_args() -> Config {
                     .id("dir")
                     .value_parser(value_parser!(PathBuf)),
                 )
+                .arg(
+                    arg!(--format <format> "Output format for the tree")
+                    .id("format")
+                    .value_parser(["ascii", "json", "dot"])
+                    .default_value("ascii")
+                )
         )
         .subcommand(
             Command::new("git-gen")
@@ -83,6 +192,88 @@ fn parse_args() -> Config {
                     .value_parser(value_parser!(String)),
                 )
         )
+        .subcommand(
+            Command::new("verify")
+                .about("Check that a previously produced version tree still matches the directory it was inferred from")
+                .arg(
+                    arg!(<dir> "Directory containing version_tree.json and the version folders/files")
+                    .id("dir")
+                    .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    arg!(-f --"files-as-versions" <extension> "Treat individual files as versions instead, with the specified extension")
+                    .id("ext")
+                    .value_parser(value_parser!(String))
+                )
+                .arg(
+                    arg!(-r --recursive "Search all subfolders (only applies to files-as-versions mode)").action(ArgAction::SetTrue)
+                )
+                .arg(
+                    arg!(--"no-multithreading" "Disable multithreading").action(ArgAction::SetTrue)
+                )
+                .arg(
+                    arg!(-w --weight <"key=value"> "Override a distance model weight (add-file, delete-file, modify-file, add-line, delete-line, line-cap); must match the weights the tree was inferred with, can be repeated")
+                    .id("weight")
+                    .action(ArgAction::Append)
+                    .value_parser(value_parser!(String))
+                )
+                .arg(
+                    arg!(--"rename-threshold" <ratio> "Minimum content-similarity ratio (0.0-1.0) for a deleted/added file pair to be reported as a rename; must match the threshold the tree was inferred with")
+                    .id("rename-threshold")
+                    .value_parser(value_parser!(f32))
+                    .default_value(DEFAULT_RENAME_SIMILARITY_THRESHOLD.to_string())
+                )
+                .arg(
+                    arg!(-e --exclude <pattern> "Glob or regex: pattern to exclude from the scan (also read from .vhiignore), can be repeated")
+                    .id("exclude")
+                    .action(ArgAction::Append)
+                    .value_parser(value_parser!(String))
+                )
+        )
+        .subcommand(
+            Command::new("update")
+                .about("Re-infer the version tree for a directory that already has a version_tree.json and divergence_cache.bin, reusing cached comparisons for unchanged versions")
+                .arg(
+                    arg!(<dir> "Directory containing version_tree.json, divergence_cache.bin, and the version folders/files")
+                    .id("dir")
+                    .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    arg!(-f --"files-as-versions" <extension> "Treat individual files as versions instead, with the specified extension")
+                    .id("ext")
+                    .value_parser(value_parser!(String))
+                )
+                .arg(
+                    arg!(-r --recursive "Search all subfolders (only applies to files-as-versions mode)").action(ArgAction::SetTrue)
+                )
+                .arg(
+                    arg!(--"no-multithreading" "Disable multithreading").action(ArgAction::SetTrue)
+                )
+                .arg(
+                    arg!(-w --weight <"key=value"> "Override a distance model weight (add-file, delete-file, modify-file, add-line, delete-line, line-cap), can be repeated")
+                    .id("weight")
+                    .action(ArgAction::Append)
+                    .value_parser(value_parser!(String))
+                )
+                .arg(
+                    arg!(--"rename-threshold" <ratio> "Minimum content-similarity ratio (0.0-1.0) for a deleted/added file pair to be reported as a rename")
+                    .id("rename-threshold")
+                    .value_parser(value_parser!(f32))
+                    .default_value(DEFAULT_RENAME_SIMILARITY_THRESHOLD.to_string())
+                )
+                .arg(
+                    arg!(--format <format> "Output format for the updated tree")
+                    .id("format")
+                    .value_parser(["ascii", "json", "dot"])
+                    .default_value("ascii")
+                )
+                .arg(
+                    arg!(-e --exclude <pattern> "Glob or regex: pattern to exclude from the scan (also read from .vhiignore), can be repeated")
+                    .id("exclude")
+                    .action(ArgAction::Append)
+                    .value_parser(value_parser!(String))
+                )
+        )
         .get_matches();
 
     match matches.subcommand() {
@@ -93,13 +284,42 @@ fn parse_args() -> Config {
             let multithreading = !submatches.get_flag("no-multithreading");
             let trace_perf = submatches.get_one::<String>("trace-perf").cloned();
             let dry_run = submatches.get_flag("dry-run");
+            let weights = submatches
+                .get_many::<String>("weight")
+                .unwrap_or_default()
+                .map(|raw| {
+                    parse_weight(raw).unwrap_or_else(|e| {
+                        eprintln!("{e}");
+                        exit(1);
+                    })
+                })
+                .collect();
+            let rename_threshold = *submatches.get_one::<f32>("rename-threshold").unwrap();
+            let format = submatches.get_one::<String>("format").unwrap().to_owned();
+            let exclude = submatches
+                .get_many::<String>("exclude")
+                .unwrap_or_default()
+                .cloned()
+                .collect();
 
-            Config::Infer(dir, ext, recursive, multithreading, trace_perf, dry_run)
+            Config::Infer(
+                dir,
+                ext,
+                recursive,
+                multithreading,
+                trace_perf,
+                dry_run,
+                weights,
+                rename_threshold,
+                format,
+                exclude,
+            )
         }
         Some(("view", submatches)) => {
             let dir = submatches.get_one::<PathBuf>("dir").unwrap().to_path_buf();
+            let format = submatches.get_one::<String>("format").unwrap().to_owned();
 
-            Config::View(dir)
+            Config::View(dir, format)
         }
         Some(("git-gen", submatches)) => {
             let dir = submatches.get_one::<PathBuf>("dir").unwrap().to_path_buf();
@@ -107,6 +327,72 @@ fn parse_args() -> Config {
 
             Config::GitGen(dir, name)
         }
+        Some(("verify", submatches)) => {
+            let dir = submatches.get_one::<PathBuf>("dir").unwrap().to_path_buf();
+            let ext = submatches.get_one::<String>("ext").cloned();
+            let recursive = submatches.get_flag("recursive");
+            let multithreading = !submatches.get_flag("no-multithreading");
+            let weights = submatches
+                .get_many::<String>("weight")
+                .unwrap_or_default()
+                .map(|raw| {
+                    parse_weight(raw).unwrap_or_else(|e| {
+                        eprintln!("{e}");
+                        exit(1);
+                    })
+                })
+                .collect();
+            let rename_threshold = *submatches.get_one::<f32>("rename-threshold").unwrap();
+            let exclude = submatches
+                .get_many::<String>("exclude")
+                .unwrap_or_default()
+                .cloned()
+                .collect();
+
+            Config::Verify(
+                dir,
+                ext,
+                recursive,
+                multithreading,
+                weights,
+                rename_threshold,
+                exclude,
+            )
+        }
+        Some(("update", submatches)) => {
+            let dir = submatches.get_one::<PathBuf>("dir").unwrap().to_path_buf();
+            let ext = submatches.get_one::<String>("ext").cloned();
+            let recursive = submatches.get_flag("recursive");
+            let multithreading = !submatches.get_flag("no-multithreading");
+            let weights = submatches
+                .get_many::<String>("weight")
+                .unwrap_or_default()
+                .map(|raw| {
+                    parse_weight(raw).unwrap_or_else(|e| {
+                        eprintln!("{e}");
+                        exit(1);
+                    })
+                })
+                .collect();
+            let rename_threshold = *submatches.get_one::<f32>("rename-threshold").unwrap();
+            let format = submatches.get_one::<String>("format").unwrap().to_owned();
+            let exclude = submatches
+                .get_many::<String>("exclude")
+                .unwrap_or_default()
+                .cloned()
+                .collect();
+
+            Config::Update(
+                dir,
+                ext,
+                recursive,
+                multithreading,
+                weights,
+                rename_threshold,
+                format,
+                exclude,
+            )
+        }
         _ => panic!("Command not recognised"), // This shouldn't happen with .subcommand_required(true)
     }
 }
@@ -123,6 +409,10 @@ fn infer(
     multithreading: bool,
     trace_perf: Option<String>,
     dry_run: bool,
+    weights: Vec<(String, f32)>,
+    rename_threshold: f32,
+    format: &str,
+    exclude: Vec<String>,
 ) {
     // Progress tracking
     let mp = MultiProgress::new();
@@ -130,8 +420,8 @@ fn infer(
 
     // Load versions
     let versions = match extension {
-        Some(ext) => load_file_versions(dir, &ext, recursive, multithreading, &mp),
-        None => load_versions(dir, multithreading, &mp),
+        Some(ext) => load_file_versions(dir, &ext, recursive, multithreading, &mp, &exclude),
+        None => load_versions(dir, multithreading, &mp, &exclude),
     }
     .unwrap_or_else(|e| {
         eprintln!("Failed to load versions: {e}");
@@ -139,8 +429,19 @@ fn infer(
     });
     perf_tracker.done_loading(&versions);
 
-    // Infer version tree
-    let version_tree = infer_version_tree(versions, multithreading, &mp);
+    let mut model = DefaultModel::default();
+    apply_weight_overrides(&mut model, &weights);
+
+    // Infer version tree, reusing cached comparisons from a previous run where possible
+    let cache_path = dir.join("divergence_cache.bin");
+    let version_tree = infer_version_tree_cached(
+        versions,
+        multithreading,
+        &mp,
+        Some(&cache_path),
+        &model,
+        rename_threshold,
+    );
     perf_tracker.done_inferring();
 
     // Save tree
@@ -161,8 +462,7 @@ fn infer(
     println!("Done in {}\n", HumanDuration(perf_tracker.elapsed()));
 
     // Output tree
-    let label_tree = produce_label_tree(&version_tree);
-    println!("{}", render(&label_tree).join("\n"));
+    print_tree(&version_tree, format);
 
     // Save performance trace
     if let Some(filename) = trace_perf {
@@ -173,6 +473,41 @@ fn infer(
     }
 }
 
+/// Re-infer a tree for a directory that's already been inferred once, reusing the
+/// `divergence_cache.bin` written by a previous `infer` so only new or changed
+/// versions need diffing. Errors out if either file is missing, since there's
+/// nothing to incrementally update from
+fn update(
+    dir: &Path,
+    extension: Option<String>,
+    recursive: bool,
+    multithreading: bool,
+    weights: Vec<(String, f32)>,
+    rename_threshold: f32,
+    format: &str,
+    exclude: Vec<String>,
+) {
+    if !dir.join("version_tree.json").exists() || !dir.join("divergence_cache.bin").exists() {
+        eprintln!(
+            "No existing version_tree.json/divergence_cache.bin found in the provided directory, run `infer` first"
+        );
+        exit(1);
+    }
+
+    infer(
+        dir,
+        extension,
+        recursive,
+        multithreading,
+        None,
+        false,
+        weights,
+        rename_threshold,
+        format,
+        exclude,
+    );
+}
+
 fn load_version_tree(dir: &Path) -> TreeNode<DiffInfo> {
     let version_tree_json = fs::read_to_string(dir.join("version_tree.json")).unwrap_or_else(|e| {
         eprintln!("Couldn't load version_tree.json from the specified directory: {e}");
@@ -186,12 +521,87 @@ fn load_version_tree(dir: &Path) -> TreeNode<DiffInfo> {
     version_tree
 }
 
-fn view(dir: &Path) {
+fn view(dir: &Path, format: &str) {
     let version_tree = load_version_tree(dir);
 
     // Output tree
-    let label_tree = produce_label_tree(&version_tree);
-    println!("{}", render(&label_tree).join("\n"));
+    print_tree(&version_tree, format);
+}
+
+fn verify(
+    dir: &Path,
+    extension: Option<String>,
+    recursive: bool,
+    multithreading: bool,
+    weights: Vec<(String, f32)>,
+    rename_threshold: f32,
+    exclude: Vec<String>,
+) {
+    let mp = MultiProgress::new();
+
+    let version_tree = load_version_tree(dir);
+
+    let versions = match extension {
+        Some(ext) => load_file_versions(dir, &ext, recursive, multithreading, &mp, &exclude),
+        None => load_versions(dir, multithreading, &mp, &exclude),
+    }
+    .unwrap_or_else(|e| {
+        eprintln!("Failed to load versions: {e}");
+        exit(1);
+    });
+
+    let mut model = DefaultModel::default();
+    apply_weight_overrides(&mut model, &weights);
+
+    let report = verify_tree(
+        &version_tree,
+        versions,
+        multithreading,
+        &mp,
+        &model,
+        rename_threshold,
+    );
+
+    if !report.stale_versions.is_empty() {
+        println!("Stale versions (in the tree but not on disk):");
+        for name in &report.stale_versions {
+            println!("  - {name}");
+        }
+    }
+
+    if !report.new_versions.is_empty() {
+        println!("New versions (on disk but not in the tree):");
+        for name in &report.new_versions {
+            println!("  - {name}");
+        }
+    }
+
+    if !report.duplicate_nodes.is_empty() {
+        println!("Names appearing more than once in the tree:");
+        for name in &report.duplicate_nodes {
+            println!("  - {name}");
+        }
+    }
+
+    if !report.cheaper_parent_violations.is_empty() {
+        println!("Edges with a cheaper available parent:");
+        for violation in &report.cheaper_parent_violations {
+            println!(
+                "  - {}: recorded parent {} (cost {:.3}), but {} is cheaper (cost {:.3})",
+                violation.version,
+                violation.recorded_parent,
+                violation.recorded_cost,
+                violation.better_parent,
+                violation.better_cost,
+            );
+        }
+    }
+
+    if report.is_ok() {
+        println!("version_tree.json is consistent with the directory.");
+    } else {
+        exit(1);
+    }
 }
 
 fn git_gen(dir: &Path, name: &str) {
@@ -216,10 +626,60 @@ fn git_gen(dir: &Path, name: &str) {
 
 fn main() {
     match parse_args() {
-        Config::Infer(dir, ext, recursive, multithreading, trace_perf, dry_run) => {
-            infer(&dir, ext, recursive, multithreading, trace_perf, dry_run)
-        }
-        Config::View(dir) => view(&dir),
+        Config::Infer(
+            dir,
+            ext,
+            recursive,
+            multithreading,
+            trace_perf,
+            dry_run,
+            weights,
+            rename_threshold,
+            format,
+            exclude,
+        ) => infer(
+            &dir,
+            ext,
+            recursive,
+            multithreading,
+            trace_perf,
+            dry_run,
+            weights,
+            rename_threshold,
+            &format,
+            exclude,
+        ),
+        Config::View(dir, format) => view(&dir, &format),
         Config::GitGen(dir, name) => git_gen(&dir, &name),
+        Config::Verify(dir, ext, recursive, multithreading, weights, rename_threshold, exclude) => {
+            verify(
+                &dir,
+                ext,
+                recursive,
+                multithreading,
+                weights,
+                rename_threshold,
+                exclude,
+            )
+        }
+        Config::Update(
+            dir,
+            ext,
+            recursive,
+            multithreading,
+            weights,
+            rename_threshold,
+            format,
+            exclude,
+        ) => update(
+            &dir,
+            ext,
+            recursive,
+            multithreading,
+            weights,
+            rename_threshold,
+            &format,
+            exclude,
+        ),
     };
 }
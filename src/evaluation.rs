@@ -0,0 +1,2 @@
+pub mod cloning;
+pub mod ingest_config;
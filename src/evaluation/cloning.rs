@@ -1,8 +1,14 @@
-use std::{fs, path::Path};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
-use dircpy::copy_dir;
-use git2::{Oid, Repository};
-use tempdir::TempDir;
+use git2::{build::RepoBuilder, Oid, Repository, Tree};
+
+use crate::inference::matcher::Matcher;
+use crate::types::{FileData, Version};
+use crate::utils::is_probably_binary;
 
 #[derive(Hash, PartialEq, Eq)]
 pub struct Commit {
@@ -10,33 +16,213 @@ pub struct Commit {
     pub name: String,
 }
 
-pub fn clone_commits_drop_git<P: AsRef<Path>>(repo_url: &str, commits: &Vec<Commit>, dest_root: P) {
-    let tmp_dir = TempDir::new("test_temp").unwrap();
-    let base = tmp_dir.path();
+/// Turn a remote URL into a filesystem-safe directory name for the mirror cache,
+/// e.g. `https://github.com/owner/repo.git` -> `https---github.com-owner-repo.git`
+fn mirror_dir_name(repo_url: &str) -> String {
+    repo_url.replace(['/', ':'], "-")
+}
+
+/// Open `repo_url`'s persistent bare mirror under `cache_root`, cloning it if this
+/// is the first time it's been seen (or `force_refresh` asked for a clean clone)
+fn open_or_create_mirror(cache_root: &Path, repo_url: &str, force_refresh: bool) -> Repository {
+    let mirror_path = cache_root.join(mirror_dir_name(repo_url));
 
-    println!("Cloning {repo_url}");
-    let repo = Repository::clone(repo_url, base).unwrap();
+    if force_refresh && mirror_path.exists() {
+        fs::remove_dir_all(&mirror_path).unwrap();
+    }
+
+    if mirror_path.exists() {
+        return Repository::open_bare(&mirror_path).unwrap();
+    }
+
+    fs::create_dir_all(cache_root).unwrap();
+    println!("Cloning {repo_url} into cache");
+    let repo = RepoBuilder::new()
+        .bare(true)
+        .clone(repo_url, &mirror_path)
+        .unwrap();
     println!("DONE");
+    repo
+}
 
-    for commit in commits {
-        let oid = match Oid::from_str(&commit.handle) {
-            Ok(oid) => oid,
-            Err(_) => {
-                let branch = repo
-                    .find_reference(&format!("refs/remotes/origin/{}", commit.handle))
-                    .unwrap();
-                branch.peel_to_commit().unwrap().id()
+/// Fetch only the commits/refs in `commits` that aren't already present in `repo`'s
+/// object database, instead of re-fetching everything
+fn fetch_missing_commits(repo: &Repository, commits: &[Commit]) {
+    let missing: Vec<&str> = commits
+        .iter()
+        .filter(|commit| match Oid::from_str(&commit.handle) {
+            Ok(oid) => repo.find_commit(oid).is_err(),
+            Err(_) => true, // not a SHA, so it's a ref that may have moved since
+        })
+        .map(|commit| commit.handle.as_str())
+        .collect();
+
+    if missing.is_empty() {
+        return;
+    }
+
+    let mut remote = repo.find_remote("origin").unwrap();
+    remote.fetch(&missing, None, None).unwrap();
+}
+
+fn resolve_commit_oid(repo: &Repository, handle: &str) -> Oid {
+    if let Ok(oid) = Oid::from_str(handle) {
+        return oid;
+    }
+    for refname in [
+        format!("refs/remotes/origin/{handle}"),
+        format!("refs/heads/{handle}"),
+    ] {
+        if let Ok(reference) = repo.find_reference(&refname) {
+            return reference.peel_to_commit().unwrap().id();
+        }
+    }
+    panic!("Could not resolve commit handle: {handle}");
+}
+
+fn walk_git_tree(
+    repo: &Repository,
+    tree: &Tree,
+    prefix: &str,
+    files: &mut HashMap<String, FileData>,
+    matcher: &Matcher,
+) {
+    for entry in tree.iter() {
+        let name = entry.name().unwrap_or_default();
+        let rel_path = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{prefix}/{name}")
+        };
+
+        let object = entry.to_object(repo).unwrap();
+        if let Some(sub_tree) = object.as_tree() {
+            if !matcher.excludes(&rel_path) {
+                walk_git_tree(repo, sub_tree, &rel_path, files, matcher);
+            }
+        } else if let Some(blob) = object.as_blob() {
+            if !matcher.matches(&rel_path) {
+                continue;
             }
+            let bytes = blob.content();
+            let digest = *blake3::hash(bytes).as_bytes();
+            let size = bytes.len() as u64;
+            let text_content = if is_probably_binary(bytes) {
+                None
+            } else {
+                std::str::from_utf8(bytes).ok().map(str::to_string)
+            };
+
+            files.insert(
+                rel_path,
+                FileData {
+                    text_content,
+                    digest: Some(digest),
+                    size,
+                },
+            );
+        }
+    }
+}
+
+/// Build `Version`s directly from each commit's tree objects, reusing a persistent
+/// bare mirror of `repo_url` under `cache_root` (cloned once, then only fetched for
+/// commits it doesn't already have) instead of cloning from scratch every time. No
+/// checkout and no per-commit directory copy are involved, unlike
+/// `clone_commits_drop_git`; prefer this when the caller (e.g. `text_diff_versions`)
+/// only needs the resulting `Version`s rather than a materialized directory on disk.
+/// `matcher` is applied while walking each tree, so excluded files never make it
+/// into `FileData` in the first place
+pub fn commit_versions_from_git(
+    repo_url: &str,
+    commits: &[Commit],
+    cache_root: &Path,
+    force_refresh: bool,
+    matcher: &Matcher,
+) -> Vec<Version> {
+    let repo = open_or_create_mirror(cache_root, repo_url, force_refresh);
+    fetch_missing_commits(&repo, commits);
+    let mirror_path: PathBuf = repo.path().to_owned();
+
+    commits
+        .iter()
+        .map(|commit| {
+            let oid = resolve_commit_oid(&repo, &commit.handle);
+            let commit_obj = repo.find_commit(oid).unwrap();
+            let tree = commit_obj.tree().unwrap();
+
+            let mut files = HashMap::new();
+            walk_git_tree(&repo, &tree, "", &mut files, matcher);
+
+            Version {
+                name: commit.name.clone(),
+                path: mirror_path.clone().into(),
+                files,
+            }
+        })
+        .collect()
+}
+
+/// Remove everything under `dir` that `matcher` doesn't keep, then remove any
+/// directory left empty by that pruning. Applied after checkout since git2's
+/// `CheckoutBuilder` has no path-predicate hook to filter files as they're written
+fn prune_checkout(dir: &Path, rel_prefix: &str, matcher: &Matcher) -> bool {
+    let mut is_empty = true;
+
+    for entry in fs::read_dir(dir).unwrap() {
+        let entry = entry.unwrap();
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let rel_path = if rel_prefix.is_empty() {
+            name
+        } else {
+            format!("{rel_prefix}/{name}")
         };
 
+        if path.is_dir() {
+            if matcher.excludes(&rel_path) || prune_checkout(&path, &rel_path, matcher) {
+                fs::remove_dir_all(&path).unwrap();
+            } else {
+                is_empty = false;
+            }
+        } else if matcher.matches(&rel_path) {
+            is_empty = false;
+        } else {
+            fs::remove_file(&path).unwrap();
+        }
+    }
+
+    is_empty
+}
+
+/// Check out each of `commits` into its own directory under `dest_root`, reusing a
+/// persistent bare mirror of `repo_url` under `cache_root` rather than re-cloning the
+/// repo on every call (see `commit_versions_from_git`). `matcher` prunes excluded
+/// files (and now-empty directories) out of each checkout afterward
+pub fn clone_commits_drop_git(
+    repo_url: &str,
+    commits: &[Commit],
+    dest_root: impl AsRef<Path>,
+    cache_root: &Path,
+    force_refresh: bool,
+    matcher: &Matcher,
+) {
+    let repo = open_or_create_mirror(cache_root, repo_url, force_refresh);
+    fetch_missing_commits(&repo, commits);
+
+    for commit in commits {
+        let oid = resolve_commit_oid(&repo, &commit.handle);
         let commit_obj = repo.find_commit(oid).unwrap();
         let tree = commit_obj.tree().unwrap();
 
-        repo.checkout_tree(tree.as_object(), None).unwrap();
-        repo.set_head_detached(oid).unwrap();
-
         let dest = dest_root.as_ref().join(&commit.name);
-        copy_dir(base, &dest).unwrap();
-        fs::remove_dir_all(&dest.join(".git")).unwrap();
+        fs::create_dir_all(&dest).unwrap();
+
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.target_dir(&dest).force();
+        repo.checkout_tree(tree.as_object(), Some(&mut checkout))
+            .unwrap();
+
+        prune_checkout(&dest, "", matcher);
     }
 }
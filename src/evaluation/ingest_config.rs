@@ -0,0 +1,83 @@
+use std::{fs, path::Path};
+
+use serde::Deserialize;
+
+use crate::evaluation::cloning::Commit;
+use crate::inference::matcher::Matcher;
+
+/// A single named commit or branch ref to resolve for a `RepoSpec`. `handle` is
+/// whatever `Commit::handle` already accepts: a SHA or a remote branch name
+#[derive(Debug, Deserialize)]
+pub struct NamedRef {
+    pub name: String,
+    pub handle: String,
+}
+
+/// One repo to ingest: where to fetch it from, which commits/refs to materialize,
+/// and which files to keep
+#[derive(Debug, Deserialize)]
+pub struct RepoSpec {
+    /// Destination subdirectory name under `IngestConfig::dest_root` for this repo
+    pub name: String,
+    pub url: String,
+    /// Used to resolve a single commit when `refs` is empty
+    #[serde(default)]
+    pub default_branch: Option<String>,
+    #[serde(default)]
+    pub refs: Vec<NamedRef>,
+    #[serde(default)]
+    pub included: Vec<String>,
+    #[serde(default)]
+    pub excluded: Vec<String>,
+}
+
+/// Top-level declarative config for `clone_fork_trees`: which repos to ingest and
+/// where to put them, replacing a hardcoded `fork_trees.json` path and destination
+#[derive(Debug, Deserialize)]
+pub struct IngestConfig {
+    pub dest_root: String,
+    #[serde(default = "default_cache_root")]
+    pub cache_root: String,
+    pub repos: Vec<RepoSpec>,
+}
+
+fn default_cache_root() -> String {
+    "./repo-cache".to_owned()
+}
+
+/// Parse an `IngestConfig` from a TOML file at `path`
+pub fn load_ingest_config(path: &Path) -> IngestConfig {
+    let contents = fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Failed to read ingest config at {}: {e}", path.display()));
+    toml::from_str(&contents)
+        .unwrap_or_else(|e| panic!("Failed to parse ingest config at {}: {e}", path.display()))
+}
+
+/// The commits to clone for `spec`: its named refs if any were given, otherwise a
+/// single commit for `default_branch` (falling back to `HEAD`)
+pub fn resolve_commits(spec: &RepoSpec) -> Vec<Commit> {
+    if !spec.refs.is_empty() {
+        return spec
+            .refs
+            .iter()
+            .map(|r| Commit {
+                handle: r.handle.clone(),
+                name: r.name.clone(),
+            })
+            .collect();
+    }
+
+    let branch = spec
+        .default_branch
+        .clone()
+        .unwrap_or_else(|| "HEAD".to_owned());
+    vec![Commit {
+        handle: branch.clone(),
+        name: branch.replace('/', "-"),
+    }]
+}
+
+/// Build the include/exclude `Matcher` for `spec`'s file scope
+pub fn matcher_for(spec: &RepoSpec) -> Matcher {
+    Matcher::new(&spec.included, &spec.excluded)
+}
@@ -0,0 +1,11 @@
+pub mod cache;
+pub mod diffing;
+pub mod distance_model;
+pub mod edmonds;
+pub mod engine;
+pub mod file_fetching;
+pub mod load_cache;
+pub mod matcher;
+pub mod snapshot;
+pub mod verify;
+pub mod version_label;
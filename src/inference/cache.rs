@@ -0,0 +1,172 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::types::DivCalcResult;
+
+const MAGIC: u32 = 0x56_48_49_43; // "VHIC" - Version History Inference Cache
+
+/// On-disk cache of pairwise `DivCalcResult`s, keyed by the content (Merkle) digest
+/// of each version, so a later run can skip re-diffing pairs it has already scored.
+/// `params_fingerprint` is an opaque fingerprint of whatever scoring parameters (the
+/// `DistanceModel` weights, the rename threshold) produced `results`; the caller is
+/// responsible for comparing it against the current parameters and discarding the
+/// cache on a mismatch, since a result scored under different weights isn't reusable
+///
+/// Layout: magic (u32) | params_fingerprint (u64) | version count n (u32) | n x
+/// 32-byte digests | n*n records of (added: u32, deleted: u32, modified: u32,
+/// divergence: f32), all little-endian, row-major so entry `i * n + j` is the result
+/// from digest `i` to digest `j`.
+#[derive(Debug, Default)]
+pub struct DivergenceCache {
+    params_fingerprint: u64,
+    digests: Vec<[u8; 32]>,
+    results: Vec<DivCalcResult>,
+}
+
+impl DivergenceCache {
+    pub fn len(&self) -> usize {
+        self.digests.len()
+    }
+
+    pub fn params_fingerprint(&self) -> u64 {
+        self.params_fingerprint
+    }
+
+    pub fn index_of(&self, digest: &[u8; 32]) -> Option<usize> {
+        self.digests.iter().position(|d| d == digest)
+    }
+
+    pub fn get(&self, from: usize, to: usize) -> DivCalcResult {
+        self.results[from * self.len() + to]
+    }
+
+    /// Build a cache to persist, from the full n x n (row-major) result matrix for
+    /// the given version digests, fingerprinted with the scoring parameters that
+    /// produced `results`
+    pub fn new(
+        params_fingerprint: u64,
+        digests: Vec<[u8; 32]>,
+        results: Vec<DivCalcResult>,
+    ) -> Self {
+        debug_assert_eq!(results.len(), digests.len() * digests.len());
+        Self {
+            params_fingerprint,
+            digests,
+            results,
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let magic = reader.read_u32::<LittleEndian>()?;
+        if magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "divergence cache has an unrecognised header",
+            ));
+        }
+        let params_fingerprint = reader.read_u64::<LittleEndian>()?;
+        let n = reader.read_u32::<LittleEndian>()? as usize;
+
+        let mut digests = Vec::with_capacity(n);
+        for _ in 0..n {
+            let mut digest = [0u8; 32];
+            reader.read_exact(&mut digest)?;
+            digests.push(digest);
+        }
+
+        let mut results = Vec::with_capacity(n * n);
+        for _ in 0..n * n {
+            results.push(DivCalcResult {
+                added: reader.read_u32::<LittleEndian>()? as usize,
+                deleted: reader.read_u32::<LittleEndian>()? as usize,
+                modified: reader.read_u32::<LittleEndian>()? as usize,
+                divergence: reader.read_f32::<LittleEndian>()?,
+            });
+        }
+
+        Ok(Self {
+            params_fingerprint,
+            digests,
+            results,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writer.write_u32::<LittleEndian>(MAGIC)?;
+        writer.write_u64::<LittleEndian>(self.params_fingerprint)?;
+        writer.write_u32::<LittleEndian>(self.digests.len() as u32)?;
+
+        for digest in &self.digests {
+            writer.write_all(digest)?;
+        }
+
+        for result in &self.results {
+            writer.write_u32::<LittleEndian>(result.added as u32)?;
+            writer.write_u32::<LittleEndian>(result.deleted as u32)?;
+            writer.write_u32::<LittleEndian>(result.modified as u32)?;
+            writer.write_f32::<LittleEndian>(result.divergence)?;
+        }
+
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let tmp_dir = TempDir::new("test_temp").unwrap();
+        let path = tmp_dir.path().join("divergence_cache.bin");
+
+        let digests = vec![[1u8; 32], [2u8; 32]];
+        let results = vec![
+            DivCalcResult::new(),
+            DivCalcResult {
+                added: 1,
+                deleted: 2,
+                modified: 3,
+                divergence: 4.5,
+            },
+            DivCalcResult {
+                added: 2,
+                deleted: 1,
+                modified: 3,
+                divergence: 6.5,
+            },
+            DivCalcResult::new(),
+        ];
+
+        let cache = DivergenceCache::new(0xDEAD_BEEF, digests.clone(), results);
+        cache.save(&path).unwrap();
+
+        let loaded = DivergenceCache::load(&path).unwrap();
+
+        assert_eq!(loaded.params_fingerprint(), 0xDEAD_BEEF);
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.index_of(&digests[0]), Some(0));
+        assert_eq!(loaded.index_of(&digests[1]), Some(1));
+        assert_eq!(loaded.index_of(&[9u8; 32]), None);
+
+        let result = loaded.get(0, 1);
+        assert_eq!(result.added, 1);
+        assert_eq!(result.deleted, 2);
+        assert_eq!(result.modified, 3);
+        assert_eq!(result.divergence, 4.5);
+
+        tmp_dir.close().unwrap();
+    }
+}
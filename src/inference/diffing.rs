@@ -1,7 +1,21 @@
 use similar::{ChangeTag, TextDiff};
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
 
-use crate::types::{FileChange, FileData, TextChange, TextualVersionDiff, Version};
+use crate::types::{
+    BinarySizes, FileChange, FileData, Hunk, RenamedFile, TextChange, TextualVersionDiff, Version,
+};
+
+/// Default minimum content similarity ratio (see `similarity_ratio`) for a
+/// deleted/added file pair to be considered a rename, mirroring git's default
+/// `-M50%`. Callers can override this via `text_diff_versions`'s `rename_threshold`
+pub const DEFAULT_RENAME_SIMILARITY_THRESHOLD: f32 = 0.5;
+
+/// Only compare file sizes within this factor of each other, unless the
+/// basenames match, to keep rename detection close to linear
+const RENAME_SIZE_FACTOR: f32 = 2.;
 
 /// Find the text changes between `old` and `new` and push them to `buffer`
 fn push_text_diff_changes(old: &str, new: &str, buffer: &mut Vec<TextChange>) {
@@ -18,40 +32,267 @@ fn push_text_diff_changes(old: &str, new: &str, buffer: &mut Vec<TextChange>) {
     );
 }
 
-/// Find what files were added/removed and what text modifications were made
-pub fn text_diff_versions(old: &Version, new: &Version) -> TextualVersionDiff {
+/// Find the text changes between `old` and `new` and push unified-diff-style hunks
+/// to `buffer`, each with up to `context` lines of unchanged `Equal` lines on either
+/// side of a change. Two change runs are merged into a single hunk when the gap of
+/// `Equal` lines between them is at most `2 * context`, mirroring how `diff -u`
+/// groups hunks. Unlike `push_text_diff_changes`, this keeps `Equal` lines that fall
+/// within a hunk's context, so the output can be rendered as a readable patch
+pub fn push_text_diff_hunks(old: &str, new: &str, context: usize, buffer: &mut Vec<Hunk>) {
+    let diff = TextDiff::from_lines(old, new);
+    let changes: Vec<TextChange> = diff
+        .iter_all_changes()
+        .map(|c| TextChange {
+            tag: c.tag(),
+            old_index: c.old_index(),
+            new_index: c.new_index(),
+            value: c.value().to_string(),
+        })
+        .collect();
+
+    // Maximal runs of non-`Equal` changes, as `[start, end)` index ranges into `changes`
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    let mut i = 0;
+    while i < changes.len() {
+        if changes[i].tag == ChangeTag::Equal {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < changes.len() && changes[i].tag != ChangeTag::Equal {
+            i += 1;
+        }
+        runs.push((start, i));
+    }
+
+    // Merge runs separated by at most `2 * context` `Equal` lines into one group
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in runs {
+        match groups.last_mut() {
+            Some((_, last_end)) if start - *last_end <= 2 * context => *last_end = end,
+            _ => groups.push((start, end)),
+        }
+    }
+
+    for (start, end) in groups {
+        let hunk_start = start.saturating_sub(context);
+        let hunk_end = (end + context).min(changes.len());
+        let lines: Vec<TextChange> = changes[hunk_start..hunk_end].to_vec();
+
+        // The first line with a known old/new index anchors the header fields; a
+        // hunk with no context may start with an insert (no old_index) or a delete
+        // (no new_index), so each side's start comes from the first line in the hunk
+        // that has an index on that side, or 0 if no line does
+        let old_start = lines
+            .iter()
+            .find_map(|l| l.old_index)
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+        let new_start = lines
+            .iter()
+            .find_map(|l| l.new_index)
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+        let old_len = lines.iter().filter(|l| l.tag != ChangeTag::Insert).count();
+        let new_len = lines.iter().filter(|l| l.tag != ChangeTag::Delete).count();
+
+        buffer.push(Hunk {
+            old_start,
+            old_len,
+            new_start,
+            new_len,
+            lines,
+        });
+    }
+}
+
+/// Content similarity between two files' text, in the range `0.0..=1.0`
+fn similarity_ratio(old: &str, new: &str) -> f32 {
+    TextDiff::from_lines(old, new).ratio()
+}
+
+fn basenames_match(a: &str, b: &str) -> bool {
+    let basename = |p: &str| Path::new(p).file_name().map(|n| n.to_owned());
+    let (Some(a), Some(b)) = (basename(a), basename(b)) else {
+        return false;
+    };
+    a == b
+}
+
+fn sizes_within_factor(a: &str, b: &str, factor: f32) -> bool {
+    let (len_a, len_b) = (a.len() as f32, b.len() as f32);
+    if len_a == 0. || len_b == 0. {
+        return false;
+    }
+    len_a.max(len_b) / len_a.min(len_b) <= factor
+}
+
+/// Greedily match deleted files against added files by content similarity, modeled on
+/// Mercurial's copy tracing: candidates are scored, sorted by descending similarity,
+/// then accepted one at a time as long as neither side has already been consumed.
+fn detect_renames(
+    old: &Version,
+    new: &Version,
+    deleted_files: &mut Vec<FileChange>,
+    added_files: &mut Vec<FileChange>,
+    rename_threshold: f32,
+) -> Vec<RenamedFile> {
+    let mut candidates: Vec<(usize, usize, f32)> = Vec::new();
+
+    for (deleted_idx, deleted) in deleted_files.iter().enumerate() {
+        let old_text = old.files[&deleted.filename]
+            .text_content
+            .as_deref()
+            .unwrap_or("");
+        if old_text.trim().is_empty() {
+            continue;
+        }
+
+        for (added_idx, added) in added_files.iter().enumerate() {
+            let new_text = new.files[&added.filename]
+                .text_content
+                .as_deref()
+                .unwrap_or("");
+            if new_text.trim().is_empty() {
+                continue;
+            }
+
+            if !sizes_within_factor(old_text, new_text, RENAME_SIZE_FACTOR)
+                && !basenames_match(&deleted.filename, &added.filename)
+            {
+                continue;
+            }
+
+            let ratio = similarity_ratio(old_text, new_text);
+            if ratio >= rename_threshold {
+                candidates.push((deleted_idx, added_idx, ratio));
+            }
+        }
+    }
+
+    candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    let mut consumed_deleted: HashSet<usize> = HashSet::new();
+    let mut consumed_added: HashSet<usize> = HashSet::new();
+    let mut renamed_files = Vec::new();
+
+    for (deleted_idx, added_idx, _) in candidates {
+        if consumed_deleted.contains(&deleted_idx) || consumed_added.contains(&added_idx) {
+            continue;
+        }
+        consumed_deleted.insert(deleted_idx);
+        consumed_added.insert(added_idx);
+
+        let old_name = deleted_files[deleted_idx].filename.to_owned();
+        let new_name = added_files[added_idx].filename.to_owned();
+
+        let old_text = old.files[&old_name].text_content.as_deref().unwrap_or("");
+        let new_text = new.files[&new_name].text_content.as_deref().unwrap_or("");
+
+        let mut changes = Vec::new();
+        push_text_diff_changes(old_text, new_text, &mut changes);
+
+        renamed_files.push(RenamedFile {
+            old_name,
+            new_name,
+            changes,
+        });
+    }
+
+    // Remove consumed files from the delete/add lists, highest index first so
+    // earlier indices stay valid as we go
+    let mut consumed_deleted: Vec<usize> = consumed_deleted.into_iter().collect();
+    consumed_deleted.sort_unstable_by(|a, b| b.cmp(a));
+    for idx in consumed_deleted {
+        deleted_files.remove(idx);
+    }
+
+    let mut consumed_added: Vec<usize> = consumed_added.into_iter().collect();
+    consumed_added.sort_unstable_by(|a, b| b.cmp(a));
+    for idx in consumed_added {
+        added_files.remove(idx);
+    }
+
+    renamed_files
+}
+
+/// Find what files were added/removed and what text modifications were made.
+/// `rename_threshold` is the minimum content-similarity ratio (see
+/// `DEFAULT_RENAME_SIMILARITY_THRESHOLD`) for a deleted/added pair to be reported as
+/// a rename instead of a separate delete and add
+pub fn text_diff_versions(
+    old: &Version,
+    new: &Version,
+    rename_threshold: f32,
+) -> TextualVersionDiff {
     let mut added_files: Vec<FileChange> = Vec::new();
     let mut deleted_files: Vec<FileChange> = Vec::new();
     let mut modified_files: Vec<FileChange> = Vec::new();
 
     // Iterate through old files to find added or modified files
     for (file_name, old_file) in old.files.iter() {
-        let old_text = old_file.text_content.as_deref().unwrap_or("");
-
         // Check for match in new files
         match new.files.get(file_name) {
             Some(new_file) => {
-                let new_text = new_file.text_content.as_deref().unwrap_or("");
-
-                if old_text != new_text {
-                    let mut changes: Vec<TextChange> = Vec::new();
-                    push_text_diff_changes(old_text, new_text, &mut changes);
-
-                    modified_files.push(FileChange {
-                        filename: file_name.to_string(),
-                        changes,
-                    });
+                // If both sides have a digest and they match, the file is byte-identical
+                // and the line diff can be skipped entirely
+                let digests_match = matches!(
+                    (old_file.digest, new_file.digest),
+                    (Some(a), Some(b)) if a == b
+                );
+
+                if !digests_match {
+                    // A line diff only makes sense when both sides are text; a binary
+                    // file (missing `text_content` on either side) with a differing
+                    // digest is still a modification, just an opaque one reported via
+                    // `binary_sizes` instead of a line-level diff
+                    match (&old_file.text_content, &new_file.text_content) {
+                        (Some(old_text), Some(new_text)) => {
+                            if old_text != new_text {
+                                let mut changes: Vec<TextChange> = Vec::new();
+                                push_text_diff_changes(old_text, new_text, &mut changes);
+
+                                modified_files.push(FileChange {
+                                    filename: file_name.to_string(),
+                                    changes,
+                                    binary_sizes: None,
+                                });
+                            }
+                        }
+                        _ => {
+                            modified_files.push(FileChange {
+                                filename: file_name.to_string(),
+                                changes: Vec::new(),
+                                binary_sizes: Some(BinarySizes {
+                                    old_size: Some(old_file.size),
+                                    new_size: Some(new_file.size),
+                                }),
+                            });
+                        }
+                    }
                 }
             }
             None => {
-                // No match in new version, file was deleted (or renamed??)
-                // TODO: Consider renamed files
-                let mut changes: Vec<TextChange> = Vec::new();
-                push_text_diff_changes(old_text, "", &mut changes);
+                // No match in new version, file was deleted (or renamed, detected below)
+                let (changes, binary_sizes) = match &old_file.text_content {
+                    Some(old_text) => {
+                        let mut changes: Vec<TextChange> = Vec::new();
+                        push_text_diff_changes(old_text, "", &mut changes);
+                        (changes, None)
+                    }
+                    None => (
+                        Vec::new(),
+                        Some(BinarySizes {
+                            old_size: Some(old_file.size),
+                            new_size: None,
+                        }),
+                    ),
+                };
 
                 deleted_files.push(FileChange {
                     filename: file_name.to_string(),
                     changes,
+                    binary_sizes,
                 });
             }
         };
@@ -63,23 +304,43 @@ pub fn text_diff_versions(old: &Version, new: &Version) -> TextualVersionDiff {
             Some(_) => (), // Already handled in previous for loop
             None => {
                 // File must have been added
-                let new_text = new_file.text_content.as_deref().unwrap_or("");
-
-                let mut changes: Vec<TextChange> = Vec::new();
-                push_text_diff_changes("", new_text, &mut changes);
+                let (changes, binary_sizes) = match &new_file.text_content {
+                    Some(new_text) => {
+                        let mut changes: Vec<TextChange> = Vec::new();
+                        push_text_diff_changes("", new_text, &mut changes);
+                        (changes, None)
+                    }
+                    None => (
+                        Vec::new(),
+                        Some(BinarySizes {
+                            old_size: None,
+                            new_size: Some(new_file.size),
+                        }),
+                    ),
+                };
 
                 added_files.push(FileChange {
                     filename: file_name.to_string(),
                     changes,
+                    binary_sizes,
                 });
             }
         };
     }
 
+    let renamed_files = detect_renames(
+        old,
+        new,
+        &mut deleted_files,
+        &mut added_files,
+        rename_threshold,
+    );
+
     return TextualVersionDiff {
         added_files,
         deleted_files,
         modified_files,
+        renamed_files,
     };
 }
 
@@ -149,6 +410,92 @@ mod tests {
         assert_eq!(changes[3].value, "ghi\n");
     }
 
+    #[test]
+    fn test_push_text_diff_hunks_single_change_with_context() {
+        let old = "a\nb\nc\nd\ne\n";
+        let new = "a\nb\nX\nd\ne\n";
+
+        let mut hunks: Vec<Hunk> = Vec::new();
+        push_text_diff_hunks(old, new, 1, &mut hunks);
+
+        assert_eq!(hunks.len(), 1);
+        let hunk = &hunks[0];
+
+        // One line of context on each side of the delete+insert pair
+        assert_eq!(hunk.old_start, 2);
+        assert_eq!(hunk.old_len, 3);
+        assert_eq!(hunk.new_start, 2);
+        assert_eq!(hunk.new_len, 3);
+
+        let tags: Vec<ChangeTag> = hunk.lines.iter().map(|l| l.tag).collect();
+        assert_eq!(
+            tags,
+            vec![
+                ChangeTag::Equal,
+                ChangeTag::Delete,
+                ChangeTag::Insert,
+                ChangeTag::Equal,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_push_text_diff_hunks_merges_nearby_changes() {
+        // Two single-line changes separated by one equal line; with context=1 the
+        // gap (1 equal line) is within 2*context (2), so they merge into one hunk
+        let old = "a\nb\nc\n";
+        let new = "X\nb\nY\n";
+
+        let mut hunks: Vec<Hunk> = Vec::new();
+        push_text_diff_hunks(old, new, 1, &mut hunks);
+
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].old_start, 1);
+        assert_eq!(hunks[0].old_len, 3);
+        assert_eq!(hunks[0].new_start, 1);
+        assert_eq!(hunks[0].new_len, 3);
+    }
+
+    #[test]
+    fn test_push_text_diff_hunks_keeps_distant_changes_separate() {
+        // Same two changes, but far enough apart (more equal lines than 2*context)
+        // that they stay as two separate hunks
+        let old = "a\nb\nc\nd\ne\nf\ng\n";
+        let new = "X\nb\nc\nd\ne\nf\nY\n";
+
+        let mut hunks: Vec<Hunk> = Vec::new();
+        push_text_diff_hunks(old, new, 1, &mut hunks);
+
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].old_start, 1);
+        assert_eq!(hunks[1].old_start, 6);
+    }
+
+    #[test]
+    fn test_push_text_diff_hunks_no_context() {
+        let old = "a\nb\nc\n";
+        let new = "a\nX\nc\n";
+
+        let mut hunks: Vec<Hunk> = Vec::new();
+        push_text_diff_hunks(old, new, 0, &mut hunks);
+
+        assert_eq!(hunks.len(), 1);
+        let hunk = &hunks[0];
+        assert_eq!(hunk.lines.len(), 2);
+        assert_eq!(hunk.old_start, 2);
+        assert_eq!(hunk.old_len, 1);
+        assert_eq!(hunk.new_start, 2);
+        assert_eq!(hunk.new_len, 1);
+    }
+
+    #[test]
+    fn test_push_text_diff_hunks_no_changes() {
+        let mut hunks: Vec<Hunk> = Vec::new();
+        push_text_diff_hunks("a\nb\n", "a\nb\n", 3, &mut hunks);
+
+        assert!(hunks.is_empty());
+    }
+
     #[test]
     fn test_text_diff_versions() {
         let s = |x: &str| Some(x.to_string());
@@ -161,12 +508,16 @@ mod tests {
                     "modified".to_string(),
                     FileData {
                         text_content: s("ok_code\n"),
+                        digest: None,
+                        size: 0,
                     },
                 ),
                 (
                     "deleted".to_string(),
                     FileData {
                         text_content: s("bad_code\n"),
+                        digest: None,
+                        size: 0,
                     },
                 ),
             ]),
@@ -179,18 +530,22 @@ mod tests {
                     "modified".to_string(),
                     FileData {
                         text_content: s("better_code\n"),
+                        digest: None,
+                        size: 0,
                     },
                 ),
                 (
                     "added".to_string(),
                     FileData {
                         text_content: s("good_code\n"),
+                        digest: None,
+                        size: 0,
                     },
                 ),
             ]),
         };
 
-        let diff = text_diff_versions(&old, &new);
+        let diff = text_diff_versions(&old, &new, DEFAULT_RENAME_SIMILARITY_THRESHOLD);
 
         assert_eq!(diff.added_files.len(), 1);
         assert_eq!(diff.added_files[0].filename, "added");
@@ -203,5 +558,232 @@ mod tests {
         assert_eq!(diff.modified_files.len(), 1);
         assert_eq!(diff.modified_files[0].filename, "modified");
         assert_eq!(diff.modified_files[0].changes.len(), 2);
+
+        assert_eq!(diff.renamed_files.len(), 0);
+    }
+
+    #[test]
+    fn test_text_diff_versions_detects_rename() {
+        let s = |x: &str| Some(x.to_string());
+
+        let shared_content = "fn main() {\n    println!(\"hello\");\n}\n";
+
+        let old = Version {
+            name: "old".to_string(),
+            path: Path::new(".").into(),
+            files: HashMap::from([(
+                "src/old_name.rs".to_string(),
+                FileData {
+                    text_content: s(shared_content),
+                    digest: None,
+                    size: 0,
+                },
+            )]),
+        };
+        let new = Version {
+            name: "new".to_string(),
+            path: Path::new(".").into(),
+            files: HashMap::from([(
+                "src/new_name.rs".to_string(),
+                FileData {
+                    text_content: s(shared_content),
+                    digest: None,
+                    size: 0,
+                },
+            )]),
+        };
+
+        let diff = text_diff_versions(&old, &new, DEFAULT_RENAME_SIMILARITY_THRESHOLD);
+
+        assert_eq!(diff.added_files.len(), 0);
+        assert_eq!(diff.deleted_files.len(), 0);
+
+        assert_eq!(diff.renamed_files.len(), 1);
+        assert_eq!(diff.renamed_files[0].old_name, "src/old_name.rs");
+        assert_eq!(diff.renamed_files[0].new_name, "src/new_name.rs");
+        assert_eq!(diff.renamed_files[0].changes.len(), 0);
+    }
+
+    #[test]
+    fn test_text_diff_versions_respects_custom_rename_threshold() {
+        let s = |x: &str| Some(x.to_string());
+
+        let old = Version {
+            name: "old".to_string(),
+            path: Path::new(".").into(),
+            files: HashMap::from([(
+                "src/old_name.rs".to_string(),
+                FileData {
+                    text_content: s("line_1\nline_2\nline_3\nline_4\n"),
+                    digest: None,
+                    size: 0,
+                },
+            )]),
+        };
+        let new = Version {
+            name: "new".to_string(),
+            path: Path::new(".").into(),
+            files: HashMap::from([(
+                "src/new_name.rs".to_string(),
+                FileData {
+                    text_content: s("line_1\nline_2\nsomething_else\nline_4\n"),
+                    digest: None,
+                    size: 0,
+                },
+            )]),
+        };
+
+        // These files are similar enough to pass the default threshold...
+        let lenient_diff = text_diff_versions(&old, &new, DEFAULT_RENAME_SIMILARITY_THRESHOLD);
+        assert_eq!(lenient_diff.renamed_files.len(), 1);
+
+        // ...but not a threshold that demands near-identical content
+        let strict_diff = text_diff_versions(&old, &new, 0.95);
+        assert_eq!(strict_diff.renamed_files.len(), 0);
+        assert_eq!(strict_diff.deleted_files.len(), 1);
+        assert_eq!(strict_diff.added_files.len(), 1);
+    }
+
+    #[test]
+    fn test_text_diff_versions_skips_diff_on_matching_digest() {
+        let old = Version {
+            name: "old".to_string(),
+            path: Path::new(".").into(),
+            files: HashMap::from([(
+                "file_a.txt".to_string(),
+                FileData {
+                    text_content: Some("stale text".to_string()),
+                    digest: Some([1; 32]),
+                    size: 0,
+                },
+            )]),
+        };
+        let new = Version {
+            name: "new".to_string(),
+            path: Path::new(".").into(),
+            files: HashMap::from([(
+                "file_a.txt".to_string(),
+                FileData {
+                    text_content: Some("completely different text".to_string()),
+                    digest: Some([1; 32]),
+                    size: 0,
+                },
+            )]),
+        };
+
+        // Digests agree, so the (deliberately inconsistent) text content is never
+        // compared and the file should be treated as unchanged
+        let diff = text_diff_versions(&old, &new, DEFAULT_RENAME_SIMILARITY_THRESHOLD);
+
+        assert_eq!(diff.modified_files.len(), 0);
+    }
+
+    #[test]
+    fn test_text_diff_versions_flags_binary_file_with_differing_digest() {
+        let old = Version {
+            name: "old".to_string(),
+            path: Path::new(".").into(),
+            files: HashMap::from([(
+                "image.png".to_string(),
+                FileData {
+                    text_content: None,
+                    digest: Some([1; 32]),
+                    size: 4,
+                },
+            )]),
+        };
+        let new = Version {
+            name: "new".to_string(),
+            path: Path::new(".").into(),
+            files: HashMap::from([(
+                "image.png".to_string(),
+                FileData {
+                    text_content: None,
+                    digest: Some([2; 32]),
+                    size: 5,
+                },
+            )]),
+        };
+
+        // Neither side has text content, so the line diff can't tell them apart, but
+        // the differing digest should still surface the file as modified
+        let diff = text_diff_versions(&old, &new, DEFAULT_RENAME_SIMILARITY_THRESHOLD);
+
+        assert_eq!(diff.modified_files.len(), 1);
+        assert_eq!(diff.modified_files[0].filename, "image.png");
+        assert_eq!(diff.modified_files[0].changes.len(), 0);
+        assert_eq!(
+            diff.modified_files[0].binary_sizes,
+            Some(BinarySizes {
+                old_size: Some(4),
+                new_size: Some(5),
+            })
+        );
+    }
+
+    #[test]
+    fn test_text_diff_versions_flags_deleted_binary_file() {
+        let old = Version {
+            name: "old".to_string(),
+            path: Path::new(".").into(),
+            files: HashMap::from([(
+                "image.png".to_string(),
+                FileData {
+                    text_content: None,
+                    digest: Some([1; 32]),
+                    size: 4,
+                },
+            )]),
+        };
+        let new = Version {
+            name: "new".to_string(),
+            path: Path::new(".").into(),
+            files: HashMap::new(),
+        };
+
+        let diff = text_diff_versions(&old, &new, DEFAULT_RENAME_SIMILARITY_THRESHOLD);
+
+        assert_eq!(diff.deleted_files.len(), 1);
+        assert_eq!(diff.deleted_files[0].changes.len(), 0);
+        assert_eq!(
+            diff.deleted_files[0].binary_sizes,
+            Some(BinarySizes {
+                old_size: Some(4),
+                new_size: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_text_diff_versions_flags_added_binary_file() {
+        let old = Version {
+            name: "old".to_string(),
+            path: Path::new(".").into(),
+            files: HashMap::new(),
+        };
+        let new = Version {
+            name: "new".to_string(),
+            path: Path::new(".").into(),
+            files: HashMap::from([(
+                "image.png".to_string(),
+                FileData {
+                    text_content: None,
+                    digest: Some([1; 32]),
+                    size: 7,
+                },
+            )]),
+        };
+
+        let diff = text_diff_versions(&old, &new, DEFAULT_RENAME_SIMILARITY_THRESHOLD);
+
+        assert_eq!(diff.added_files.len(), 1);
+        assert_eq!(diff.added_files[0].changes.len(), 0);
+        assert_eq!(
+            diff.added_files[0].binary_sizes,
+            Some(BinarySizes {
+                old_size: None,
+                new_size: Some(7),
+            })
+        );
     }
 }
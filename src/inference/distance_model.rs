@@ -0,0 +1,189 @@
+use crate::types::{Pair, TextChange, TextualVersionDiff};
+use similar::ChangeTag;
+use std::hash::{Hash, Hasher};
+
+/// Scores how costly it is to treat one version as derived from another, from the
+/// textual diff between them. Swapping the implementation lets `infer_version_tree`
+/// be tuned for different corpora (e.g. code vs. prose) without a recompile
+pub trait DistanceModel: Sync {
+    fn edge_cost(&self, diff: &TextualVersionDiff) -> Pair;
+
+    /// A fingerprint of this model's tunable weights, used to invalidate a
+    /// `DivergenceCache` that was written under different weights (see
+    /// `engine::compute_divergence_matrix`)
+    fn fingerprint(&self) -> u64;
+}
+
+fn count_tag(changes: &Vec<TextChange>, tag: ChangeTag) -> usize {
+    changes.iter().filter(|c| c.tag == tag).count()
+}
+
+/// The model `infer_version_tree` used before weights became configurable: fixed
+/// per-file penalties, plus a per-line penalty capped at `line_cap` lines so one
+/// wildly different file can't dominate the whole comparison
+#[derive(Debug, Clone)]
+pub struct DefaultModel {
+    pub add_file: f32,
+    pub delete_file: f32,
+    pub modify_file: f32,
+    pub add_line: f32,
+    pub delete_line: f32,
+    pub line_cap: usize,
+}
+
+impl Default for DefaultModel {
+    fn default() -> Self {
+        Self {
+            add_file: 2.,
+            delete_file: 4.,
+            modify_file: 1.,
+            add_line: 0.02,
+            delete_line: 0.05,
+            line_cap: 50,
+        }
+    }
+}
+
+impl DefaultModel {
+    fn file_heuristic(&self, changes: &Vec<TextChange>) -> Pair {
+        let adds = count_tag(changes, ChangeTag::Insert).min(self.line_cap) as f32;
+        let deletes = count_tag(changes, ChangeTag::Delete).min(self.line_cap) as f32;
+
+        Pair(
+            adds * self.add_line + deletes * self.delete_line,
+            adds * self.delete_line + deletes * self.add_line,
+        )
+    }
+}
+
+impl DistanceModel for DefaultModel {
+    fn edge_cost(&self, diff: &TextualVersionDiff) -> Pair {
+        let mut forward_backward = Pair(0., 0.);
+
+        for file_change in &diff.added_files {
+            forward_backward += Pair(self.add_file, self.delete_file);
+            forward_backward += self.file_heuristic(&file_change.changes);
+        }
+
+        for file_change in &diff.deleted_files {
+            forward_backward += Pair(self.delete_file, self.add_file);
+            forward_backward += self.file_heuristic(&file_change.changes);
+        }
+
+        for file_change in &diff.modified_files {
+            forward_backward += Pair(self.modify_file, self.modify_file);
+            forward_backward += self.file_heuristic(&file_change.changes);
+        }
+
+        // Renamed/copied files are charged like a modification of the residual diff
+        // rather than a full add+delete, so restructuring a tree doesn't inflate
+        // divergence
+        for renamed_file in &diff.renamed_files {
+            forward_backward += Pair(self.modify_file, self.modify_file);
+            forward_backward += self.file_heuristic(&renamed_file.changes);
+        }
+
+        forward_backward
+    }
+
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.add_file.to_bits().hash(&mut hasher);
+        self.delete_file.to_bits().hash(&mut hasher);
+        self.modify_file.to_bits().hash(&mut hasher);
+        self.add_line.to_bits().hash(&mut hasher);
+        self.delete_line.to_bits().hash(&mut hasher);
+        self.line_cap.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::types::RenamedFile;
+
+    fn text_change(tag: ChangeTag) -> TextChange {
+        TextChange {
+            tag,
+            old_index: None,
+            new_index: None,
+            value: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_default_model_edge_cost() {
+        let model = DefaultModel::default();
+
+        let diff = TextualVersionDiff {
+            added_files: vec![],
+            deleted_files: vec![],
+            modified_files: vec![crate::types::FileChange {
+                filename: "a.txt".to_owned(),
+                changes: vec![
+                    text_change(ChangeTag::Insert),
+                    text_change(ChangeTag::Delete),
+                ],
+                binary_sizes: None,
+            }],
+            renamed_files: vec![],
+        };
+
+        let Pair(forward, backward) = model.edge_cost(&diff);
+
+        // The per-line penalties are summed before being added to the per-file one
+        assert_eq!(
+            forward,
+            model.modify_file + (model.add_line + model.delete_line)
+        );
+        assert_eq!(
+            backward,
+            model.modify_file + (model.delete_line + model.add_line)
+        );
+    }
+
+    #[test]
+    fn test_default_model_weights_are_configurable() {
+        let model = DefaultModel {
+            add_file: 10.,
+            ..DefaultModel::default()
+        };
+
+        let diff = TextualVersionDiff {
+            added_files: vec![crate::types::FileChange {
+                filename: "a.txt".to_owned(),
+                changes: vec![],
+                binary_sizes: None,
+            }],
+            deleted_files: vec![],
+            modified_files: vec![],
+            renamed_files: vec![],
+        };
+
+        let Pair(forward, _) = model.edge_cost(&diff);
+        assert_eq!(forward, 10.);
+    }
+
+    #[test]
+    fn test_default_model_counts_renames_as_residual_modifications() {
+        let model = DefaultModel::default();
+
+        let diff = TextualVersionDiff {
+            added_files: vec![],
+            deleted_files: vec![],
+            modified_files: vec![],
+            renamed_files: vec![RenamedFile {
+                old_name: "a.txt".to_owned(),
+                new_name: "b.txt".to_owned(),
+                changes: vec![],
+            }],
+        };
+
+        let Pair(forward, backward) = model.edge_cost(&diff);
+        assert_eq!(forward, model.modify_file);
+        assert_eq!(backward, model.modify_file);
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use chu_liu_edmonds::chu_liu_edmonds;
 use ndarray::{arr2, ArrayView2};
 
@@ -9,6 +11,60 @@ pub fn find_msa(scores: ArrayView2<f32>, root_vertex: usize) -> Vec<Option<usize
     return chu_liu_edmonds(new_scores.view(), root_vertex);
 }
 
+/// Collect every descendant of `node` (exclusive of `node` itself) given a tree in
+/// children-list form
+pub(crate) fn collect_descendants(children: &[Vec<usize>], node: usize, out: &mut HashSet<usize>) {
+    for &child in &children[node] {
+        if out.insert(child) {
+            collect_descendants(children, child, out);
+        }
+    }
+}
+
+/// Same as `find_msa`, but alongside the parent vector also returns a confidence
+/// margin for each non-root node: how much cheaper the chosen incoming edge was than
+/// the best alternative parent that wouldn't have introduced a cycle. A margin near
+/// zero means the arborescence could easily have picked a different, near-identical
+/// parent, so it's worth a human double-checking that branch point
+pub fn find_msa_with_margins(
+    scores: ArrayView2<f32>,
+    root_vertex: usize,
+) -> (Vec<Option<usize>>, Vec<Option<f32>>) {
+    let parents = find_msa(scores, root_vertex);
+    let n = parents.len();
+
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (node, parent) in parents.iter().enumerate() {
+        if let Some(p) = parent {
+            children[*p].push(node);
+        }
+    }
+
+    let margins = parents
+        .iter()
+        .enumerate()
+        .map(|(v, parent)| {
+            let chosen = (*parent)?;
+            let chosen_cost = scores[(chosen, v)];
+
+            // Any candidate parent that is v itself or a descendant of v would form a
+            // cycle, so it's excluded from the alternative search
+            let mut excluded = HashSet::from([v]);
+            collect_descendants(&children, v, &mut excluded);
+
+            (0..n)
+                .filter(|candidate| *candidate != chosen && !excluded.contains(candidate))
+                .map(|candidate| scores[(candidate, v)])
+                .fold(None, |best: Option<f32>, cost| {
+                    Some(best.map_or(cost, |b| b.min(cost)))
+                })
+                .map(|alt_cost| alt_cost - chosen_cost)
+        })
+        .collect();
+
+    (parents, margins)
+}
+
 fn msa_to_string(result: &Vec<Option<usize>>) -> String {
     result
         .iter()
@@ -70,4 +126,38 @@ mod tests {
 
         assert_eq!(msa_to_string(&res), "_, 2, 3, 0");
     }
+
+    #[test]
+    fn test_find_msa_with_margins_flags_a_tie() {
+        // Vertex 1 has two equally cheap candidate parents (0 and 2), so whichever one
+        // the arborescence picks, its margin should come out at zero. Vertex 2 hangs
+        // off 0 rather than 1, so it is never a descendant of 1 and stays a valid
+        // alternative
+        let divergence_graph = arr2(&[
+            [0., 1., 1., 100.],
+            [100., 0., 100., 100.],
+            [100., 1., 0., 1.],
+            [100., 100., 100., 0.],
+        ]);
+
+        let (parents, margins) = find_msa_with_margins(divergence_graph.view(), 0);
+
+        assert_eq!(parents[0], None);
+        assert!(matches!(parents[1], Some(0) | Some(2)));
+        assert_eq!(margins[0], None);
+        assert_eq!(margins[1], Some(0.));
+    }
+
+    #[test]
+    fn test_find_msa_with_margins_excludes_descendants() {
+        let divergence_graph = arr2(&[[0., 1., 100.], [100., 0., 1.], [100., 2., 0.]]);
+
+        let (parents, margins) = find_msa_with_margins(divergence_graph.view(), 0);
+
+        assert_eq!(msa_to_string(&parents), "_, 0, 1");
+        // Vertex 1's only other candidate parent is vertex 2, which is its own
+        // descendant, so no valid alternative exists
+        assert_eq!(margins[1], None);
+        assert_eq!(margins[2], Some(100. - 1.));
+    }
 }
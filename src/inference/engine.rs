@@ -1,21 +1,21 @@
 use crate::{
     inference::{
-        diffing::text_diff_versions,
-        edmonds::{assemble_forest, find_msa},
+        cache::DivergenceCache,
+        diffing::{text_diff_versions, DEFAULT_RENAME_SIMILARITY_THRESHOLD},
+        distance_model::{DefaultModel, DistanceModel},
+        edmonds::{assemble_forest, find_msa_with_margins},
         file_fetching::load_versions,
+        version_label::{compare_order, is_semver_successor, parse_version_label, VersionLabel},
     },
-    types::{
-        DiffInfo, DivCalcResult, FileChange, Pair, TextChange, TextualVersionDiff, TreeNode,
-        Version,
-    },
+    types::{DiffInfo, DivCalcResult, TextualVersionDiff, TreeNode, Version},
     utils::PB_BAR_STYLE,
 };
 use indicatif::{MultiProgress, ProgressBar};
 use ndarray::Array2;
 use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
-use similar::ChangeTag;
 use std::{
-    collections::HashMap,
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
     io,
     path::Path,
     sync::Arc,
@@ -23,44 +23,15 @@ use std::{
     time::Duration,
 };
 
-// Penalties
-const ADD_FILE_P: f32 = 2.;
-const DELETE_FILE_P: f32 = 4.;
-const MODIFY_FILE_P: f32 = 1.;
-const ADD_LINE_P: f32 = 0.02;
-const DELETE_LINE_P: f32 = 0.05;
-
-fn count_tag(changes: &Vec<TextChange>, tag: ChangeTag) -> usize {
-    changes.iter().filter(|c| c.tag == tag).count()
-}
-
-fn file_heuristic(file_change: &FileChange) -> Pair {
-    let adds = count_tag(&file_change.changes, ChangeTag::Insert).min(50) as f32;
-    let deletes = count_tag(&file_change.changes, ChangeTag::Delete).min(50) as f32;
-
-    Pair(
-        adds * ADD_LINE_P + deletes * DELETE_LINE_P,
-        adds * DELETE_LINE_P + deletes * ADD_LINE_P,
-    )
-}
+// Edge priors derived from parsed version labels (see `version_label`)
+const SEMVER_SUCCESSOR_REWARD: f32 = 5.;
+const BACKWARDS_ORDER_PENALTY: f32 = 1000.;
 
-pub fn calculate_divergences(text_diff: &TextualVersionDiff) -> (DivCalcResult, DivCalcResult) {
-    let mut forward_backward = Pair(0., 0.);
-
-    for file_change in &text_diff.added_files {
-        forward_backward += Pair(ADD_FILE_P, DELETE_FILE_P);
-        forward_backward += file_heuristic(file_change);
-    }
-
-    for file_change in &text_diff.deleted_files {
-        forward_backward += Pair(DELETE_FILE_P, ADD_FILE_P);
-        forward_backward += file_heuristic(file_change);
-    }
-
-    for file_change in &text_diff.modified_files {
-        forward_backward += Pair(MODIFY_FILE_P, MODIFY_FILE_P);
-        forward_backward += file_heuristic(file_change);
-    }
+pub fn calculate_divergences(
+    model: &dyn DistanceModel,
+    text_diff: &TextualVersionDiff,
+) -> (DivCalcResult, DivCalcResult) {
+    let forward_backward = model.edge_cost(text_diff);
 
     let added = text_diff.added_files.len();
     let deleted = text_diff.deleted_files.len();
@@ -83,11 +54,124 @@ pub fn calculate_divergences(text_diff: &TextualVersionDiff) -> (DivCalcResult,
     (forward, backward)
 }
 
+/// Fingerprint the scoring parameters (`model`'s weights plus `rename_threshold`)
+/// that a `DivergenceCache` was (or would be) produced under, so a cache written
+/// under different parameters can be detected and invalidated rather than silently
+/// reused
+fn cache_params_fingerprint(model: &dyn DistanceModel, rename_threshold: f32) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    model.fingerprint().hash(&mut hasher);
+    rename_threshold.to_bits().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Adjust the `from -> to` edge weight in `divergences` based on the parsed version
+/// labels: reward an immediate SemVer successor, and heavily penalise an edge that
+/// runs strictly backwards in whatever ordering the labels do agree on
+fn apply_label_bias(
+    divergences: &mut Array2<f32>,
+    from: usize,
+    to: usize,
+    from_label: &VersionLabel,
+    to_label: &VersionLabel,
+) {
+    if is_semver_successor(from_label, to_label) {
+        divergences[(from, to)] = (divergences[(from, to)] - SEMVER_SUCCESSOR_REWARD).max(0.);
+    }
+
+    if compare_order(from_label, to_label) == Some(std::cmp::Ordering::Greater) {
+        divergences[(from, to)] += BACKWARDS_ORDER_PENALTY;
+    }
+}
+
 pub fn infer_version_tree(
-    mut versions: Vec<Version>,
+    versions: Vec<Version>,
+    multithreading: bool,
+    mp: &MultiProgress,
+) -> TreeNode<DiffInfo> {
+    infer_version_tree_cached(
+        versions,
+        multithreading,
+        mp,
+        None,
+        &DefaultModel::default(),
+        DEFAULT_RENAME_SIMILARITY_THRESHOLD,
+    )
+}
+
+/// Same as `infer_version_tree`, but reuses a `DivergenceCache` persisted at
+/// `cache_path` (if present) to skip re-diffing version pairs whose content digests
+/// were already scored on a previous run, and rewrites it with the full result
+/// afterwards. This lets an existing tree be extended with one or two new forks
+/// without repeating the O(n^2) comparison pass over versions that haven't changed.
+/// `model` scores the cost of each edge from its textual diff; swap it out to tune
+/// inference for a different kind of corpus. `rename_threshold` is the minimum
+/// content-similarity ratio for a deleted/added file pair to be treated as a rename
+/// (see `diffing::text_diff_versions`)
+pub fn infer_version_tree_cached(
+    versions: Vec<Version>,
     multithreading: bool,
     mp: &MultiProgress,
+    cache_path: Option<&Path>,
+    model: &dyn DistanceModel,
+    rename_threshold: f32,
 ) -> TreeNode<DiffInfo> {
+    let (versions, duplicates_of, divergences, div_calc_res) = compute_divergence_matrix(
+        versions,
+        multithreading,
+        mp,
+        cache_path,
+        model,
+        rename_threshold,
+    );
+
+    let (msa, margins) = find_msa_with_margins(divergences.view(), 0);
+    let mut forest = assemble_forest(&msa, None);
+
+    assert_eq!(forest.len(), 1, "MSA is not tree");
+    let tree = forest.remove(0);
+
+    // Convert tree of indexes to DiffInfo tree
+    let mut diff_tree = tree.map_with_parent(
+        &|&i, parent| {
+            // (i, i) will just give a null difference (all zeroes)
+            let p = parent.cloned().unwrap_or(i);
+            let forward = div_calc_res[(p, i)];
+            DiffInfo {
+                name: versions[i].name.to_owned(),
+                added: forward.added,
+                deleted: forward.deleted,
+                modified: forward.modified,
+                divergence: forward.divergence,
+                margin: margins[i],
+            }
+        },
+        None,
+    );
+
+    attach_duplicates(&mut diff_tree, &duplicates_of);
+
+    diff_tree
+}
+
+/// Inserts the synthetic "Empty" root, collapses byte-for-byte duplicate versions,
+/// and computes the pairwise divergence matrix (content divergence plus version-label
+/// bias) for the resulting unique versions. This is the same matrix `infer_version_tree`
+/// builds its arborescence from, so `verify` can recompute it to sanity-check a
+/// previously-inferred tree against the current state of a directory
+pub fn compute_divergence_matrix(
+    mut versions: Vec<Version>,
+    multithreading: bool,
+    mp: &MultiProgress,
+    cache_path: Option<&Path>,
+    model: &dyn DistanceModel,
+    rename_threshold: f32,
+) -> (
+    Vec<Version>,
+    HashMap<String, Vec<String>>,
+    Array2<f32>,
+    Array2<DivCalcResult>,
+) {
     let null_version = Version {
         name: "Empty".to_string(),
         path: Path::new(".").into(), // TODO: Is this safe?
@@ -95,7 +179,50 @@ pub fn infer_version_tree(
     };
     versions.insert(0, null_version);
 
+    // Collapse versions with identical content (same Merkle digest over their files)
+    // before the O(n^2) comparison pass; duplicates are reattached below as
+    // zero-divergence children of their representative once the tree is built
+    let mut digest_to_unique_idx: HashMap<[u8; 32], usize> = HashMap::new();
+    let mut unique_versions: Vec<Version> = Vec::new();
+    let mut duplicates_of: HashMap<String, Vec<String>> = HashMap::new();
+
+    for version in versions {
+        let digest = version.merkle_digest();
+        match digest_to_unique_idx.get(&digest) {
+            Some(&idx) => {
+                duplicates_of
+                    .entry(unique_versions[idx].name.to_owned())
+                    .or_default()
+                    .push(version.name);
+            }
+            None => {
+                digest_to_unique_idx.insert(digest, unique_versions.len());
+                unique_versions.push(version);
+            }
+        }
+    }
+    let versions = unique_versions;
+
     let n = versions.len();
+    let version_digests: Vec<[u8; 32]> = versions.iter().map(Version::merkle_digest).collect();
+
+    let params_fingerprint = cache_params_fingerprint(model, rename_threshold);
+
+    let loaded_cache = cache_path
+        .filter(|path| path.exists())
+        .and_then(|path| DivergenceCache::load(path).ok());
+    let cache = match loaded_cache {
+        Some(cache) if cache.params_fingerprint() == params_fingerprint => cache,
+        Some(_) => {
+            eprintln!(
+                "Divergence cache at {:?} was written with different model weights or rename \
+                 threshold; ignoring it and recomputing from scratch",
+                cache_path.unwrap()
+            );
+            DivergenceCache::default()
+        }
+        None => DivergenceCache::default(),
+    };
 
     let versions_arc = Arc::new(versions);
 
@@ -112,12 +239,22 @@ pub fn infer_version_tree(
     cmp_pb.enable_steady_tick(Duration::from_millis(100));
 
     let map_op = |&(i, j): &(usize, usize)| {
+        // Reuse a previously computed result if both versions' content digests are
+        // already in the cache, rather than re-running the (expensive) text diff
+        if let (Some(ci), Some(cj)) = (
+            cache.index_of(&version_digests[i]),
+            cache.index_of(&version_digests[j]),
+        ) {
+            cmp_pb.inc(1);
+            return (i, j, (cache.get(ci, cj), cache.get(cj, ci)));
+        }
+
         let version_a = &versions_arc[i];
         let version_b = &versions_arc[j];
 
-        let text_diff = text_diff_versions(version_a, version_b);
+        let text_diff = text_diff_versions(version_a, version_b, rename_threshold);
         cmp_pb.inc(1);
-        (i, j, calculate_divergences(&text_diff))
+        (i, j, calculate_divergences(model, &text_diff))
     };
 
     let results = if multithreading {
@@ -145,30 +282,70 @@ pub fn infer_version_tree(
     }
     cmp_pb.finish();
 
+    if let Some(path) = cache_path {
+        let new_cache = DivergenceCache::new(
+            params_fingerprint,
+            version_digests,
+            div_calc_res.clone().into_raw_vec(),
+        );
+        if let Err(e) = new_cache.save(path) {
+            eprintln!("Failed to save divergence cache: {e}");
+        }
+    }
+
+    // Bias the distance matrix with whatever ordering is encoded in the version
+    // names, on top of the purely content-based divergence. This only steers which
+    // edges the arborescence picks; it isn't part of the cached/reported DivCalcResult
+    let version_labels: Vec<VersionLabel> = versions_arc
+        .iter()
+        .map(|v| parse_version_label(&v.name))
+        .collect();
+    for j in 1..n {
+        for i in 0..j {
+            apply_label_bias(
+                &mut divergences,
+                i,
+                j,
+                &version_labels[i],
+                &version_labels[j],
+            );
+            apply_label_bias(
+                &mut divergences,
+                j,
+                i,
+                &version_labels[j],
+                &version_labels[i],
+            );
+        }
+    }
+
     let versions = Arc::try_unwrap(versions_arc).unwrap();
 
-    let msa = find_msa(divergences.view(), 0);
-    let mut forest = assemble_forest(&msa, None);
+    (versions, duplicates_of, divergences, div_calc_res)
+}
 
-    assert_eq!(forest.len(), 1, "MSA is not tree");
-    let tree = forest.remove(0);
+/// Reattach versions that were collapsed as exact content duplicates, as
+/// zero-divergence children of the representative version they were merged into
+fn attach_duplicates(node: &mut TreeNode<DiffInfo>, duplicates_of: &HashMap<String, Vec<String>>) {
+    if let Some(dup_names) = duplicates_of.get(&node.value.name) {
+        for dup_name in dup_names {
+            node.children.push(TreeNode {
+                value: DiffInfo {
+                    name: dup_name.to_owned(),
+                    added: 0,
+                    deleted: 0,
+                    modified: 0,
+                    divergence: 0.,
+                    margin: None,
+                },
+                children: vec![],
+            });
+        }
+    }
 
-    // Convert tree of indexes to DiffInfo tree
-    tree.map_with_parent(
-        &|&i, parent| {
-            // (i, i) will just give a null difference (all zeroes)
-            let p = parent.cloned().unwrap_or(i);
-            let forward = div_calc_res[(p, i)];
-            DiffInfo {
-                name: versions[i].name.to_owned(),
-                added: forward.added,
-                deleted: forward.deleted,
-                modified: forward.modified,
-                divergence: forward.divergence,
-            }
-        },
-        None,
-    )
+    for child in &mut node.children {
+        attach_duplicates(child, duplicates_of);
+    }
 }
 
 #[cfg(test)]
@@ -205,7 +382,7 @@ mod tests {
         append_to_file(base.join("version_3/file_b.txt"), "xyz\n").unwrap();
 
         let mp = &MultiProgress::new();
-        let versions = load_versions(base, true, &mp).unwrap();
+        let versions = load_versions(base, true, &mp, &[]).unwrap();
         let version_tree = infer_version_tree(versions, true, &mp);
         let name_tree = version_tree.map(&|v| v.name.to_owned());
 
@@ -268,7 +445,7 @@ mod tests {
         .unwrap();
 
         let mp = &MultiProgress::new();
-        let versions = load_versions(base, true, &mp).unwrap();
+        let versions = load_versions(base, true, &mp, &[]).unwrap();
         let version_tree = infer_version_tree(versions, true, &mp);
         let name_tree = version_tree.map(&|v| v.name.to_owned());
 
@@ -293,4 +470,126 @@ mod tests {
 
         tmp_dir.close().unwrap();
     }
+
+    #[test]
+    fn handcrafted_3_duplicate_version_collapsed() {
+        let tmp_dir = TempDir::new("test_temp").unwrap();
+        let base = tmp_dir.path();
+
+        fs::create_dir_all(base.join("version_1")).unwrap();
+        fs::write(base.join("version_1/file_a.txt"), "file_a\n").unwrap();
+
+        // Byte-for-byte duplicate of version_1
+        copy_dir(base.join("version_1"), base.join("version_1_copy")).unwrap();
+
+        copy_dir(base.join("version_1"), base.join("version_2")).unwrap();
+        append_to_file(base.join("version_2/file_a.txt"), "abc\n").unwrap();
+
+        let mp = &MultiProgress::new();
+        let versions = load_versions(base, true, &mp, &[]).unwrap();
+        let version_tree = infer_version_tree(versions, true, &mp);
+        let name_tree = version_tree.map(&|v| v.name.to_owned());
+
+        let expected = TreeNode {
+            value: "Empty".to_owned(),
+            children: vec![TreeNode {
+                value: "version_1".to_owned(),
+                children: vec![
+                    TreeNode {
+                        value: "version_2".to_owned(),
+                        children: vec![],
+                    },
+                    TreeNode {
+                        value: "version_1_copy".to_owned(),
+                        children: vec![],
+                    },
+                ],
+            }],
+        };
+
+        assert_eq!(name_tree, expected);
+
+        tmp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn handcrafted_4_reruns_from_cache() {
+        let tmp_dir = TempDir::new("test_temp").unwrap();
+        let base = tmp_dir.path();
+
+        fs::create_dir_all(base.join("version_1")).unwrap();
+        fs::write(base.join("version_1/file_a.txt"), "file_a\n").unwrap();
+
+        copy_dir(base.join("version_1"), base.join("version_2")).unwrap();
+        append_to_file(base.join("version_2/file_a.txt"), "abc\n").unwrap();
+
+        let cache_path = base.join("divergence_cache.bin");
+
+        let mp = &MultiProgress::new();
+        let model = DefaultModel::default();
+        let versions = load_versions(base, true, &mp, &[]).unwrap();
+        let first_tree = infer_version_tree_cached(
+            versions,
+            true,
+            &mp,
+            Some(&cache_path),
+            &model,
+            DEFAULT_RENAME_SIMILARITY_THRESHOLD,
+        );
+
+        assert!(cache_path.exists());
+
+        // Add a third version after the cache has been written; rerunning should
+        // reuse the cached version_1/version_2 comparison and only score the new one
+        copy_dir(base.join("version_2"), base.join("version_3")).unwrap();
+        append_to_file(base.join("version_3/file_a.txt"), "def\n").unwrap();
+
+        let versions = load_versions(base, true, &mp, &[]).unwrap();
+        let second_tree = infer_version_tree_cached(
+            versions,
+            true,
+            &mp,
+            Some(&cache_path),
+            &model,
+            DEFAULT_RENAME_SIMILARITY_THRESHOLD,
+        );
+
+        let name_tree = second_tree.map(&|v| v.name.to_owned());
+        let first_name_tree = first_tree.map(&|v| v.name.to_owned());
+
+        // version_1 -> version_2 subtree is unchanged by the rerun
+        assert_eq!(
+            name_tree.children[0].value,
+            first_name_tree.children[0].value
+        );
+        assert_eq!(
+            name_tree.children[0].children[0].value,
+            first_name_tree.children[0].children[0].value
+        );
+
+        assert!(tree_contains(&name_tree, "version_3"));
+
+        tmp_dir.close().unwrap();
+    }
+
+    fn tree_contains(node: &TreeNode<String>, name: &str) -> bool {
+        node.value == name || node.children.iter().any(|c| tree_contains(c, name))
+    }
+
+    #[test]
+    fn test_apply_label_bias() {
+        use crate::inference::version_label::parse_version_label;
+
+        let v1_0_0 = parse_version_label("v1.0.0");
+        let v1_0_1 = parse_version_label("v1.0.1");
+
+        let mut divergences = Array2::from_elem((2, 2), 10.);
+        apply_label_bias(&mut divergences, 0, 1, &v1_0_0, &v1_0_1);
+        apply_label_bias(&mut divergences, 1, 0, &v1_0_1, &v1_0_0);
+
+        // v1.0.0 -> v1.0.1 is an immediate successor, so its cost is reduced
+        assert_eq!(divergences[(0, 1)], 10. - SEMVER_SUCCESSOR_REWARD);
+        // v1.0.1 -> v1.0.0 runs backwards, so it's made prohibitively expensive
+        assert_eq!(divergences[(1, 0)], 10. + BACKWARDS_ORDER_PENALTY);
+    }
 }
@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, DirEntry};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::inference::load_cache::{LoadCache, LoadCacheWriter, Stat, MANIFEST_FILE_NAME};
+use crate::inference::matcher::{read_ignore_file, Matcher, IGNORE_FILE_NAME};
+use crate::types::{FileData, Version};
+use crate::utils::{is_probably_binary, PB_BAR_STYLE};
+
+/// Build a `Matcher` for a scan rooted at `root`: `exclude_patterns` combined with
+/// whatever `.vhiignore` (if any) is found directly in `root`, plus the tool's own
+/// dotfiles (the ignore file and the load-cache manifest), which are never part of
+/// the versioned content being compared
+fn matcher_for_root(root: &Path, exclude_patterns: &[String]) -> Matcher {
+    let mut exclude = read_ignore_file(root);
+    exclude.push(format!("/{IGNORE_FILE_NAME}"));
+    exclude.push(format!("/{MANIFEST_FILE_NAME}"));
+    exclude.extend(exclude_patterns.iter().cloned());
+    Matcher::new(&[], &exclude)
+}
+
+fn walk_dir(
+    dir: &Path,
+    rel_prefix: &str,
+    file_paths: &mut Vec<Box<Path>>,
+    extension: Option<&str>,
+    recursive: bool,
+    matcher: &Matcher,
+) -> io::Result<()> {
+    if dir.is_dir() {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let rel_path = if rel_prefix.is_empty() {
+                name
+            } else {
+                format!("{rel_prefix}/{name}")
+            };
+
+            if path.is_dir() {
+                if recursive && !matcher.excludes(&rel_path) {
+                    walk_dir(&path, &rel_path, file_paths, extension, true, matcher)?;
+                }
+            } else {
+                if let Some(expected_ext) = extension {
+                    let Some(actual_ext) = path.extension().or(path.file_name()) else {
+                        continue;
+                    };
+                    if actual_ext.to_string_lossy() != expected_ext {
+                        continue;
+                    }
+                }
+                if !matcher.matches(&rel_path) {
+                    continue;
+                }
+                file_paths.push(path.into());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn dirs_in_dir(dir: &Path) -> io::Result<Vec<Box<Path>>> {
+    let mut dir_paths: Vec<Box<Path>> = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            dir_paths.push(path.into());
+        }
+    }
+
+    Ok(dir_paths)
+}
+
+/// Read a file's bytes and return its text content (if it looks like text, for
+/// diffing; see `is_probably_binary`) alongside a content digest and byte length
+/// (always, so binary files still affect divergence)
+fn read_file(path: &Path) -> io::Result<(Option<String>, [u8; 32], u64)> {
+    let bytes = fs::read(path)?;
+    let digest = *blake3::hash(&bytes).as_bytes();
+    let size = bytes.len() as u64;
+    let text_content = if is_probably_binary(&bytes) {
+        None
+    } else {
+        String::from_utf8(bytes).ok()
+    };
+    Ok((text_content, digest, size))
+}
+
+fn get_relative_path<'a>(path: &'a Path, base: &'a Path) -> &'a Path {
+    path.strip_prefix(&base)
+        .expect("Failed to strip path prefix")
+}
+
+/// Read `path`, reusing `cache`'s entry for `rel_path` when its stat still matches
+/// instead of reading and re-hashing the file. Returns the resulting `FileData`
+/// alongside the stat it was read/reused under, so the caller can record it into a
+/// fresh manifest
+fn read_file_cached(
+    path: &Path,
+    rel_path: &str,
+    cache: &LoadCache,
+) -> io::Result<(FileData, Stat)> {
+    let stat = Stat::read(path)?;
+
+    if let Some(file_data) = cache.lookup(rel_path, &stat) {
+        return Ok((file_data, stat));
+    }
+
+    let (text_content, digest, size) = read_file(path)?;
+    let file_data = FileData {
+        text_content,
+        digest: Some(digest),
+        size,
+    };
+    Ok((file_data, stat))
+}
+
+pub fn load_versions(
+    dir: &Path,
+    multithreading: bool,
+    mp: &MultiProgress,
+    exclude_patterns: &[String],
+) -> io::Result<Vec<Version>> {
+    let version_paths = dirs_in_dir(dir)?;
+    let mut versions: Vec<Version> = Vec::new();
+
+    let pb = mp.add(ProgressBar::new(version_paths.len() as u64));
+    pb.set_style(PB_BAR_STYLE.clone());
+    pb.set_prefix("Loading versions");
+    pb.enable_steady_tick(Duration::from_millis(100));
+
+    for (i, version_path) in version_paths.into_iter().enumerate() {
+        let matcher = matcher_for_root(&version_path, exclude_patterns);
+        let mut file_paths: Vec<Box<Path>> = Vec::new();
+        walk_dir(&version_path, "", &mut file_paths, None, true, &matcher)?;
+
+        let version_pb = Arc::new(mp.add(ProgressBar::new(file_paths.len() as u64)));
+        version_pb.set_style(PB_BAR_STYLE.clone());
+        version_pb.set_prefix(format!("Version {}", i + 1));
+
+        let manifest_path = version_path.join(MANIFEST_FILE_NAME);
+        let load_cache = LoadCache::load(&manifest_path);
+
+        let map_op = |file_path: &Box<Path>| {
+            let file_rel_path = get_relative_path(&file_path, &version_path)
+                .to_string_lossy()
+                .to_string();
+            let (file_data, stat) = read_file_cached(file_path, &file_rel_path, &load_cache)?;
+            version_pb.inc(1);
+            Ok((file_rel_path, file_data, stat))
+        };
+
+        let read_files: Vec<(String, FileData, Stat)> = if multithreading {
+            file_paths
+                .par_iter()
+                .map(map_op)
+                .collect::<io::Result<_>>()?
+        } else {
+            file_paths.iter().map(map_op).collect::<io::Result<_>>()?
+        };
+
+        let mut files = HashMap::with_capacity(read_files.len());
+        let mut cache_writer = LoadCacheWriter::new();
+        for (rel_path, file_data, stat) in read_files {
+            cache_writer.record(
+                rel_path.clone(),
+                stat,
+                file_data.digest.unwrap_or([0; 32]),
+                file_data.text_content.clone(),
+            );
+            files.insert(rel_path, file_data);
+        }
+        cache_writer.save(&manifest_path)?;
+
+        let version_rel_path = get_relative_path(&version_path, &dir);
+        let version_name = version_rel_path.to_string_lossy().to_string();
+
+        versions.push(Version {
+            name: version_name,
+            path: version_path,
+            files,
+        });
+
+        pb.inc(1);
+    }
+
+    pb.finish();
+
+    Ok(versions)
+}
+
+pub fn load_file_versions(
+    dir: &Path,
+    extension: &str,
+    recursive: bool,
+    multithreading: bool,
+    mp: &MultiProgress,
+    exclude_patterns: &[String],
+) -> io::Result<Vec<Version>> {
+    let norm_ext = extension.strip_prefix(".").unwrap_or(extension);
+    let matcher = matcher_for_root(dir, exclude_patterns);
+
+    let mut file_paths = vec![];
+    walk_dir(
+        dir,
+        "",
+        &mut file_paths,
+        Some(norm_ext),
+        recursive,
+        &matcher,
+    )?;
+
+    let pb = mp.add(ProgressBar::new(file_paths.len() as u64));
+    pb.set_style(PB_BAR_STYLE.clone());
+    pb.set_prefix("Loading versions");
+    pb.enable_steady_tick(Duration::from_millis(100));
+
+    let manifest_path = dir.join(MANIFEST_FILE_NAME);
+    let load_cache = LoadCache::load(&manifest_path);
+
+    let read_files: Vec<(String, Box<Path>, FileData, Stat)> = file_paths
+        .par_iter()
+        .map(|file_path| {
+            let version_rel_path = get_relative_path(&file_path, &dir)
+                .to_string_lossy()
+                .to_string();
+
+            let (file_data, stat) = read_file_cached(file_path, &version_rel_path, &load_cache)?;
+            pb.inc(1);
+            Ok((version_rel_path, file_path.clone(), file_data, stat))
+        })
+        .collect::<io::Result<_>>()?;
+
+    let mut cache_writer = LoadCacheWriter::new();
+    let mut files = Vec::with_capacity(read_files.len());
+    for (version_name, file_path, file_data, stat) in read_files {
+        cache_writer.record(
+            version_name.clone(),
+            stat,
+            file_data.digest.unwrap_or([0; 32]),
+            file_data.text_content.clone(),
+        );
+        files.push(Version {
+            name: version_name,
+            path: file_path,
+            files: HashMap::from([("main".to_string(), file_data)]),
+        });
+    }
+    cache_writer.save(&manifest_path)?;
+
+    pb.finish();
+
+    Ok(files)
+}
+
+/// A `Version` loaded from a Git commit, alongside the SHAs of that commit's parents
+/// so an evaluation mode can compare the inferred tree against the true commit graph
+#[derive(Debug)]
+pub struct GitVersion {
+    pub version: Version,
+    pub parents: Vec<String>,
+}
+
+fn walk_git_tree(
+    tree: &gix::Tree,
+    prefix: &str,
+    files: &mut HashMap<String, FileData>,
+) -> Result<(), Box<dyn Error>> {
+    for entry in tree.iter() {
+        let entry = entry?;
+        let rel_path = if prefix.is_empty() {
+            entry.filename().to_string()
+        } else {
+            format!("{prefix}/{}", entry.filename())
+        };
+
+        let object = entry.object()?;
+        if let Ok(sub_tree) = object.clone().try_into_tree() {
+            walk_git_tree(&sub_tree, &rel_path, files)?;
+        } else if let Ok(blob) = object.try_into_blob() {
+            let bytes = blob.data.clone();
+            let digest = *blake3::hash(&bytes).as_bytes();
+            let size = bytes.len() as u64;
+            let text_content = if is_probably_binary(&bytes) {
+                None
+            } else {
+                String::from_utf8(bytes).ok()
+            };
+
+            files.insert(
+                rel_path,
+                FileData {
+                    text_content,
+                    digest: Some(digest),
+                    size,
+                },
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Load versions directly from commits in a Git repository's object store, with no
+/// checkout and no temporary directories: each requested revision's tree is walked
+/// in-memory and its blobs become `FileData`, just like `load_versions` does for a
+/// directory of working trees
+pub fn load_versions_from_git(
+    repo_dir: &Path,
+    revs: &[String],
+) -> Result<Vec<GitVersion>, Box<dyn Error>> {
+    let repo = gix::open(repo_dir)?;
+
+    let mut versions = Vec::with_capacity(revs.len());
+
+    for rev in revs {
+        let commit = repo.rev_parse_single(rev.as_str())?.object()?.into_commit();
+        let tree = commit.tree()?;
+
+        let mut files = HashMap::new();
+        walk_git_tree(&tree, "", &mut files)?;
+
+        let parents = commit.parent_ids().map(|id| id.to_string()).collect();
+
+        versions.push(GitVersion {
+            version: Version {
+                name: rev.to_owned(),
+                path: repo_dir.into(),
+                files,
+            },
+            parents,
+        });
+    }
+
+    Ok(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_load_versions() {
+        let tmp_dir = TempDir::new("test_temp").unwrap();
+        let base = tmp_dir.path();
+
+        fs::create_dir_all(base.join("version_1")).unwrap();
+        fs::create_dir_all(base.join("version_2")).unwrap();
+        fs::write(base.join("version_1/file_a.txt"), "file_a").unwrap();
+        fs::write(base.join("version_1/file_b.txt"), "file_b").unwrap();
+        fs::write(base.join("version_2/file_a.txt"), "file_a_new").unwrap();
+        fs::write(base.join("version_2/file_b.txt"), "file_b_new").unwrap();
+
+        let versions = load_versions(base, true, &MultiProgress::new(), &[]).unwrap();
+
+        assert_eq!(versions.len(), 2);
+
+        let version_1 = versions.iter().find(|v| v.name == "version_1").unwrap();
+        assert_eq!(version_1.path, base.join("version_1").into());
+        let files_1 = &version_1.files;
+        assert_eq!(
+            files_1["file_a.txt"].text_content.as_ref().unwrap(),
+            "file_a"
+        );
+        assert_eq!(
+            files_1["file_b.txt"].text_content.as_ref().unwrap(),
+            "file_b"
+        );
+
+        let version_2 = versions.iter().find(|v| v.name == "version_2").unwrap();
+        assert_eq!(version_2.path, base.join("version_2").into());
+        let files_2 = &version_2.files;
+        assert_eq!(
+            files_2["file_a.txt"].text_content.as_ref().unwrap(),
+            "file_a_new"
+        );
+        assert_eq!(
+            files_2["file_b.txt"].text_content.as_ref().unwrap(),
+            "file_b_new"
+        );
+
+        tmp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_load_file_versions() {
+        let tmp_dir = TempDir::new("test_temp").unwrap();
+        let base = tmp_dir.path();
+
+        fs::create_dir_all(base.join("dir")).unwrap();
+        fs::write(base.join("file_a.txt"), "file_a").unwrap();
+        fs::write(base.join("dir/file_b.txt"), "file_b").unwrap();
+        fs::write(base.join("excluded.abc"), "excluded").unwrap();
+
+        let mp = MultiProgress::new();
+        let versions = load_file_versions(base, "txt", true, true, &mp, &[]).unwrap();
+
+        assert_eq!(versions.len(), 2);
+
+        let v1 = versions.iter().find(|v| v.name == "file_a.txt").unwrap();
+        assert_eq!(v1.path, base.join("file_a.txt").into());
+        assert_eq!(v1.files["main"].text_content.as_ref().unwrap(), "file_a");
+
+        let v2 = versions
+            .iter()
+            .find(|v| v.name == PathBuf::from("dir").join("file_b.txt").to_string_lossy())
+            .unwrap();
+        assert_eq!(v2.path, base.join("dir/file_b.txt").into());
+        assert_eq!(v2.files["main"].text_content.as_ref().unwrap(), "file_b");
+
+        tmp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_load_versions_writes_manifest_and_is_excluded_from_files() {
+        let tmp_dir = TempDir::new("test_temp").unwrap();
+        let base = tmp_dir.path();
+
+        fs::create_dir_all(base.join("version_1")).unwrap();
+        fs::write(base.join("version_1/file_a.txt"), "file_a").unwrap();
+
+        let versions = load_versions(base, true, &MultiProgress::new(), &[]).unwrap();
+
+        let version_1 = versions.iter().find(|v| v.name == "version_1").unwrap();
+        assert!(base.join("version_1").join(MANIFEST_FILE_NAME).exists());
+        assert!(!version_1.files.contains_key(MANIFEST_FILE_NAME));
+
+        tmp_dir.close().unwrap();
+    }
+}
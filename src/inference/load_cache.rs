@@ -0,0 +1,300 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::types::FileData;
+
+const MAGIC: u32 = 0x56_48_49_4C; // "VHIL" - Version History Inference Load cache
+
+/// Name of the manifest file a scan writes into the directory it just scanned
+pub const MANIFEST_FILE_NAME: &str = ".vhi_load_cache.bin";
+
+/// A file's on-disk identity, truncated the way Mercurial's dirstate truncates
+/// mtimes: to 31 bits, since some platforms can't round-trip a full 32-bit
+/// timestamp, plus nanoseconds for sub-second resolution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stat {
+    pub size: u64,
+    pub mtime_secs: u32,
+    pub mtime_nanos: u32,
+}
+
+impl Stat {
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata.modified()?;
+        let since_epoch = modified
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        Ok(Self {
+            size: metadata.len(),
+            mtime_secs: (since_epoch.as_secs() as u32) & 0x7FFF_FFFF,
+            mtime_nanos: since_epoch.subsec_nanos(),
+        })
+    }
+}
+
+struct Entry {
+    stat: Stat,
+    /// Set when this entry's mtime fell in the same second the manifest was
+    /// written, so a same-second edit can't be distinguished from the read that
+    /// produced this entry; such an entry is never trusted on the next load
+    ambiguous: bool,
+    digest: [u8; 32],
+    text_content: Option<String>,
+}
+
+/// Manifest of `(path, stat) -> FileData` consulted by `load_versions`/
+/// `load_file_versions` to skip re-reading and re-hashing files whose size and
+/// mtime haven't changed since the manifest was written
+#[derive(Default)]
+pub struct LoadCache {
+    entries: HashMap<String, Entry>,
+}
+
+impl LoadCache {
+    pub fn load(path: &Path) -> Self {
+        Self::try_load(path).unwrap_or_default()
+    }
+
+    fn try_load(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let magic = reader.read_u32::<LittleEndian>()?;
+        if magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "load cache has an unrecognised header",
+            ));
+        }
+
+        let n = reader.read_u32::<LittleEndian>()? as usize;
+        let mut entries = HashMap::with_capacity(n);
+
+        for _ in 0..n {
+            let path_len = reader.read_u32::<LittleEndian>()? as usize;
+            let mut path_bytes = vec![0u8; path_len];
+            reader.read_exact(&mut path_bytes)?;
+            let rel_path = String::from_utf8(path_bytes)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            let size = reader.read_u64::<LittleEndian>()?;
+            let mtime_secs = reader.read_u32::<LittleEndian>()?;
+            let mtime_nanos = reader.read_u32::<LittleEndian>()?;
+            let ambiguous = reader.read_u8()? != 0;
+
+            let mut digest = [0u8; 32];
+            reader.read_exact(&mut digest)?;
+
+            let has_text = reader.read_u8()? != 0;
+            let text_content = if has_text {
+                let text_len = reader.read_u32::<LittleEndian>()? as usize;
+                let mut text_bytes = vec![0u8; text_len];
+                reader.read_exact(&mut text_bytes)?;
+                Some(
+                    String::from_utf8(text_bytes)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+                )
+            } else {
+                None
+            };
+
+            entries.insert(
+                rel_path,
+                Entry {
+                    stat: Stat {
+                        size,
+                        mtime_secs,
+                        mtime_nanos,
+                    },
+                    ambiguous,
+                    digest,
+                    text_content,
+                },
+            );
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Return the cached `FileData` for `rel_path` if its recorded stat matches
+    /// `current` and the entry wasn't written in the same second it was read
+    pub fn lookup(&self, rel_path: &str, current: &Stat) -> Option<FileData> {
+        let entry = self.entries.get(rel_path)?;
+        if entry.ambiguous || entry.stat != *current {
+            return None;
+        }
+
+        Some(FileData {
+            text_content: entry.text_content.clone(),
+            digest: Some(entry.digest),
+            size: entry.stat.size,
+        })
+    }
+}
+
+/// Accumulates entries for a fresh manifest as files are (re-)read, then writes it
+/// out once the scan completes
+pub struct LoadCacheWriter {
+    entries: HashMap<String, Entry>,
+    write_time_secs: u32,
+}
+
+impl LoadCacheWriter {
+    pub fn new() -> Self {
+        let write_time_secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as u32
+            & 0x7FFF_FFFF;
+
+        Self {
+            entries: HashMap::new(),
+            write_time_secs,
+        }
+    }
+
+    pub fn record(&mut self, rel_path: String, stat: Stat, digest: [u8; 32], text_content: Option<String>) {
+        let ambiguous = stat.mtime_secs == self.write_time_secs;
+        self.entries.insert(
+            rel_path,
+            Entry {
+                stat,
+                ambiguous,
+                digest,
+                text_content,
+            },
+        );
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writer.write_u32::<LittleEndian>(MAGIC)?;
+        writer.write_u32::<LittleEndian>(self.entries.len() as u32)?;
+
+        for (rel_path, entry) in &self.entries {
+            let path_bytes = rel_path.as_bytes();
+            writer.write_u32::<LittleEndian>(path_bytes.len() as u32)?;
+            writer.write_all(path_bytes)?;
+
+            writer.write_u64::<LittleEndian>(entry.stat.size)?;
+            writer.write_u32::<LittleEndian>(entry.stat.mtime_secs)?;
+            writer.write_u32::<LittleEndian>(entry.stat.mtime_nanos)?;
+            writer.write_u8(entry.ambiguous as u8)?;
+            writer.write_all(&entry.digest)?;
+
+            match &entry.text_content {
+                Some(text) => {
+                    writer.write_u8(1)?;
+                    writer.write_u32::<LittleEndian>(text.len() as u32)?;
+                    writer.write_all(text.as_bytes())?;
+                }
+                None => writer.write_u8(0)?,
+            }
+        }
+
+        writer.flush()
+    }
+}
+
+impl Default for LoadCacheWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let tmp_dir = TempDir::new("test_temp").unwrap();
+        let path = tmp_dir.path().join(MANIFEST_FILE_NAME);
+
+        let stat = Stat {
+            size: 123,
+            mtime_secs: 1_700_000_000,
+            mtime_nanos: 42,
+        };
+
+        let mut writer = LoadCacheWriter::new();
+        writer.record(
+            "file_a.txt".to_string(),
+            stat,
+            [7u8; 32],
+            Some("hello".to_string()),
+        );
+        writer.save(&path).unwrap();
+
+        let cache = LoadCache::load(&path);
+
+        assert_eq!(
+            cache.lookup("file_a.txt", &stat).unwrap().text_content,
+            Some("hello".to_string())
+        );
+        assert!(cache.lookup("missing.txt", &stat).is_none());
+
+        tmp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_lookup_rejects_mismatched_stat() {
+        let tmp_dir = TempDir::new("test_temp").unwrap();
+        let path = tmp_dir.path().join(MANIFEST_FILE_NAME);
+
+        let stat = Stat {
+            size: 123,
+            mtime_secs: 1_700_000_000,
+            mtime_nanos: 0,
+        };
+
+        let mut writer = LoadCacheWriter::new();
+        writer.record("file_a.txt".to_string(), stat, [7u8; 32], None);
+        writer.save(&path).unwrap();
+
+        let cache = LoadCache::load(&path);
+
+        let changed_stat = Stat {
+            size: 124,
+            ..stat
+        };
+        assert!(cache.lookup("file_a.txt", &changed_stat).is_none());
+
+        tmp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_entry_written_in_same_second_as_manifest_is_ambiguous() {
+        let tmp_dir = TempDir::new("test_temp").unwrap();
+        let path = tmp_dir.path().join(MANIFEST_FILE_NAME);
+
+        let mut writer = LoadCacheWriter::new();
+        let stat = Stat {
+            size: 10,
+            mtime_secs: writer.write_time_secs,
+            mtime_nanos: 0,
+        };
+        writer.record("file_a.txt".to_string(), stat, [1u8; 32], None);
+        writer.save(&path).unwrap();
+
+        let cache = LoadCache::load(&path);
+
+        // The file's mtime lands in the same second the manifest was written, so
+        // it must never be trusted, even though the stat otherwise matches
+        assert!(cache.lookup("file_a.txt", &stat).is_none());
+
+        tmp_dir.close().unwrap();
+    }
+}
@@ -0,0 +1,177 @@
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+
+/// Name of the optional file in a scanned root that holds newline-separated exclude
+/// patterns, read the same way `.gitignore` is: blank lines and `#` comments skipped
+pub const IGNORE_FILE_NAME: &str = ".vhiignore";
+
+/// A single include/exclude pattern, compiled to an anchored regex over `/`-separated
+/// relative paths. A leading `/` roots the pattern at the scan root (like gitignore);
+/// without one it matches at any depth
+struct Pattern {
+    regex: Regex,
+}
+
+impl Pattern {
+    fn parse(raw: &str) -> Pattern {
+        let (body, rooted) = match raw.strip_prefix('/') {
+            Some(rest) => (rest, true),
+            None => (raw, false),
+        };
+
+        let core = match body.strip_prefix("regex:") {
+            Some(raw_regex) => raw_regex.to_owned(),
+            None => glob_to_regex(body),
+        };
+
+        let anchored = if rooted {
+            format!("^{core}(?:$|/.*)")
+        } else {
+            format!("(?:^|.*/){core}(?:$|/.*)")
+        };
+
+        Pattern {
+            regex: Regex::new(&anchored).unwrap_or_else(|e| {
+                panic!("Invalid pattern '{raw}': {e}");
+            }),
+        }
+    }
+
+    fn is_match(&self, rel_path: &str) -> bool {
+        self.regex.is_match(rel_path)
+    }
+}
+
+/// Translate a shell-style glob into a regex fragment: `*` matches within one path
+/// component, `**/` matches zero or more whole directories, a standalone `**` matches
+/// across components, `?` matches a single non-separator character, and everything
+/// else is matched literally. `**/` and `**` are substituted for private-use
+/// placeholder characters first so the single-`*`/`?` pass below doesn't have to
+/// special-case them
+fn glob_to_regex(glob: &str) -> String {
+    const DOUBLE_STAR_SLASH: char = '\u{E000}';
+    const DOUBLE_STAR: char = '\u{E001}';
+
+    let normalized = glob
+        .replace("**/", &DOUBLE_STAR_SLASH.to_string())
+        .replace("**", &DOUBLE_STAR.to_string());
+
+    let mut out = String::new();
+    for c in normalized.chars() {
+        match c {
+            DOUBLE_STAR_SLASH => out.push_str("(?:.*/)?"),
+            DOUBLE_STAR => out.push_str(".*"),
+            '*' => out.push_str("[^/]*"),
+            '?' => out.push_str("[^/]"),
+            _ => out.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    out
+}
+
+/// Include/exclude pattern set for pruning files (and whole directories) out of a
+/// version scan, modelled on Mercurial's filepattern/matcher design: shell globs and
+/// `regex:`-prefixed raw regexes, either rooted or matching at any depth
+#[derive(Default)]
+pub struct Matcher {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl Matcher {
+    pub fn new(include: &[String], exclude: &[String]) -> Self {
+        Self {
+            include: include.iter().map(|p| Pattern::parse(p)).collect(),
+            exclude: exclude.iter().map(|p| Pattern::parse(p)).collect(),
+        }
+    }
+
+    /// Whether `rel_path` should be kept: it matches an include pattern (or there are
+    /// none, meaning include-everything) and no exclude pattern
+    pub fn matches(&self, rel_path: &str) -> bool {
+        let included =
+            self.include.is_empty() || self.include.iter().any(|p| p.is_match(rel_path));
+        included && !self.excludes(rel_path)
+    }
+
+    /// Whether `rel_path` matches an exclude pattern on its own; used to prune whole
+    /// directories during recursion without descending into them at all
+    pub fn excludes(&self, rel_path: &str) -> bool {
+        self.exclude.iter().any(|p| p.is_match(rel_path))
+    }
+}
+
+/// Read exclude patterns from `.vhiignore` in `root`, if present
+pub fn read_ignore_file(root: &Path) -> Vec<String> {
+    let Ok(contents) = fs::read_to_string(root.join(IGNORE_FILE_NAME)) else {
+        return Vec::new();
+    };
+
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_owned)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_glob_star_matches_within_component() {
+        let matcher = Matcher::new(&[], &["*.log".to_owned()]);
+
+        assert!(matcher.excludes("debug.log"));
+        assert!(matcher.excludes("nested/debug.log"));
+        assert!(!matcher.excludes("debug.logs"));
+    }
+
+    #[test]
+    fn test_rooted_pattern_only_matches_at_root() {
+        let matcher = Matcher::new(&[], &["/build".to_owned()]);
+
+        assert!(matcher.excludes("build"));
+        assert!(matcher.excludes("build/output.bin"));
+        assert!(!matcher.excludes("nested/build"));
+    }
+
+    #[test]
+    fn test_double_star_matches_across_components() {
+        let matcher = Matcher::new(&[], &["**/node_modules".to_owned()]);
+
+        assert!(matcher.excludes("node_modules"));
+        assert!(matcher.excludes("a/b/node_modules"));
+        assert!(matcher.excludes("a/b/node_modules/pkg/index.js"));
+    }
+
+    #[test]
+    fn test_raw_regex_pattern() {
+        let matcher = Matcher::new(&[], &["regex:.*\\.generated\\..*".to_owned()]);
+
+        assert!(matcher.excludes("src/foo.generated.rs"));
+        assert!(!matcher.excludes("src/foo.rs"));
+    }
+
+    #[test]
+    fn test_include_patterns_restrict_to_matches() {
+        let matcher = Matcher::new(&["*.rs".to_owned()], &[]);
+
+        assert!(matcher.matches("main.rs"));
+        assert!(!matcher.matches("README.md"));
+    }
+
+    #[test]
+    fn test_exclude_takes_priority_over_include() {
+        let matcher = Matcher::new(&["*".to_owned()], &["*.log".to_owned()]);
+
+        assert!(matcher.matches("main.rs"));
+        assert!(!matcher.matches("debug.log"));
+    }
+}
@@ -0,0 +1,504 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::types::{FileData, Version};
+
+const MAGIC: u32 = 0x56_48_49_53; // "VHIS" - Version History Inference Snapshot
+const FORMAT_VERSION: u8 = 1;
+
+const HEADER_LEN: usize = 13; // magic(4) + format_version(1) + version_count(4) + file_count(4)
+const VERSION_RECORD_LEN: usize = 24; // 6 x u32
+const FILE_RECORD_LEN: usize = 58; // 4+4 + 1+32 + 8 + 1+4+4
+
+/// A snapshot is corrupt, or doesn't describe a version set this build understands.
+/// Carries the record/offset that failed so a bad file is easy to locate
+#[derive(Debug)]
+pub struct SnapshotError {
+    context: String,
+    source: io::Error,
+}
+
+impl SnapshotError {
+    fn new(context: impl Into<String>, source: io::Error) -> Self {
+        Self {
+            context: context.into(),
+            source,
+        }
+    }
+
+    fn invalid(context: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(
+            context,
+            io::Error::new(io::ErrorKind::InvalidData, message.into()),
+        )
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "malformed snapshot at {}: {}", self.context, self.source)
+    }
+}
+
+impl std::error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Appends variable-length byte strings to a flat blob buffer, handing back the
+/// `(offset, len)` pair each one was written at so a fixed-size record can
+/// reference it without embedding the bytes inline
+#[derive(Default)]
+struct BlobWriter {
+    bytes: Vec<u8>,
+}
+
+impl BlobWriter {
+    fn push(&mut self, data: &[u8]) -> (u32, u32) {
+        let offset = self.bytes.len() as u32;
+        self.bytes.extend_from_slice(data);
+        (offset, data.len() as u32)
+    }
+}
+
+struct RawFileRecord {
+    rel_path_offset: u32,
+    rel_path_len: u32,
+    digest: Option<[u8; 32]>,
+    size: u64,
+    text_offset: u32,
+    text_len: u32,
+    has_text: bool,
+}
+
+struct RawVersionRecord {
+    name_offset: u32,
+    name_len: u32,
+    path_offset: u32,
+    path_len: u32,
+    file_start: u32,
+    file_count: u32,
+}
+
+/// Write `versions` to `path` as a single self-describing binary snapshot: a fixed
+/// header, a flat region of fixed-size version/file records, and a trailing blob
+/// region that the records reference by offset+length (version names, relative
+/// file paths, and inline text; digests are small enough to live in the record
+/// itself). This mirrors dirstate-v2's layout rather than a generic serde dump, so
+/// a reader can jump straight to one version's records without decoding the rest
+pub fn save_snapshot(path: &Path, versions: &[Version]) -> io::Result<()> {
+    let mut blob = BlobWriter::default();
+    let mut version_records = Vec::with_capacity(versions.len());
+    let mut file_records = Vec::new();
+
+    for version in versions {
+        let (name_offset, name_len) = blob.push(version.name.as_bytes());
+        let path_str = version.path.to_string_lossy();
+        let (path_offset, path_len) = blob.push(path_str.as_bytes());
+
+        let file_start = file_records.len() as u32;
+        for (rel_path, file_data) in &version.files {
+            let (rel_path_offset, rel_path_len) = blob.push(rel_path.as_bytes());
+            let (text_offset, text_len, has_text) = match &file_data.text_content {
+                Some(text) => {
+                    let (offset, len) = blob.push(text.as_bytes());
+                    (offset, len, true)
+                }
+                None => (0, 0, false),
+            };
+
+            file_records.push(RawFileRecord {
+                rel_path_offset,
+                rel_path_len,
+                digest: file_data.digest,
+                size: file_data.size,
+                text_offset,
+                text_len,
+                has_text,
+            });
+        }
+
+        version_records.push(RawVersionRecord {
+            name_offset,
+            name_len,
+            path_offset,
+            path_len,
+            file_start,
+            file_count: version.files.len() as u32,
+        });
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    writer.write_u32::<LittleEndian>(MAGIC)?;
+    writer.write_u8(FORMAT_VERSION)?;
+    writer.write_u32::<LittleEndian>(version_records.len() as u32)?;
+    writer.write_u32::<LittleEndian>(file_records.len() as u32)?;
+
+    for record in &version_records {
+        writer.write_u32::<LittleEndian>(record.name_offset)?;
+        writer.write_u32::<LittleEndian>(record.name_len)?;
+        writer.write_u32::<LittleEndian>(record.path_offset)?;
+        writer.write_u32::<LittleEndian>(record.path_len)?;
+        writer.write_u32::<LittleEndian>(record.file_start)?;
+        writer.write_u32::<LittleEndian>(record.file_count)?;
+    }
+
+    for record in &file_records {
+        writer.write_u32::<LittleEndian>(record.rel_path_offset)?;
+        writer.write_u32::<LittleEndian>(record.rel_path_len)?;
+        writer.write_u8(record.digest.is_some() as u8)?;
+        writer.write_all(&record.digest.unwrap_or([0; 32]))?;
+        writer.write_u64::<LittleEndian>(record.size)?;
+        writer.write_u8(record.has_text as u8)?;
+        writer.write_u32::<LittleEndian>(record.text_offset)?;
+        writer.write_u32::<LittleEndian>(record.text_len)?;
+    }
+
+    writer.write_all(&blob.bytes)?;
+    writer.flush()
+}
+
+/// Slice `blob` at `offset..offset+len`, wrapping an out-of-bounds reference in a
+/// `SnapshotError` that names the record it came from instead of panicking
+fn slice_blob<'a>(
+    blob: &'a [u8],
+    offset: u32,
+    len: u32,
+    context: &str,
+) -> Result<&'a [u8], SnapshotError> {
+    let start = offset as usize;
+    let end = start + len as usize;
+    blob.get(start..end).ok_or_else(|| {
+        SnapshotError::invalid(
+            context.to_owned(),
+            format!(
+                "blob range {start}..{end} is out of bounds (blob is {} bytes)",
+                blob.len()
+            ),
+        )
+    })
+}
+
+fn blob_str<'a>(
+    blob: &'a [u8],
+    offset: u32,
+    len: u32,
+    context: &str,
+) -> Result<&'a str, SnapshotError> {
+    let bytes = slice_blob(blob, offset, len, context)?;
+    std::str::from_utf8(bytes).map_err(|e| {
+        SnapshotError::new(
+            context.to_owned(),
+            io::Error::new(io::ErrorKind::InvalidData, e),
+        )
+    })
+}
+
+/// Load a snapshot previously written by `save_snapshot`, reconstructing the
+/// `Vec<Version>` it described
+pub fn load_snapshot(path: &Path) -> Result<Vec<Version>, SnapshotError> {
+    let buf = fs::read(path).map_err(|e| SnapshotError::new("snapshot file", e))?;
+    let mut cursor = io::Cursor::new(&buf);
+
+    if buf.len() < HEADER_LEN {
+        return Err(SnapshotError::invalid(
+            "header",
+            format!(
+                "file is only {} bytes, shorter than the {HEADER_LEN}-byte header",
+                buf.len()
+            ),
+        ));
+    }
+
+    let magic = cursor
+        .read_u32::<LittleEndian>()
+        .map_err(|e| SnapshotError::new("header magic", e))?;
+    if magic != MAGIC {
+        return Err(SnapshotError::invalid(
+            "header magic",
+            "unrecognised snapshot header",
+        ));
+    }
+
+    let format_version = cursor
+        .read_u8()
+        .map_err(|e| SnapshotError::new("header format version", e))?;
+    if format_version != FORMAT_VERSION {
+        return Err(SnapshotError::invalid(
+            "header format version",
+            format!("snapshot is format version {format_version}, this build only reads {FORMAT_VERSION}"),
+        ));
+    }
+
+    let version_count = cursor
+        .read_u32::<LittleEndian>()
+        .map_err(|e| SnapshotError::new("header version count", e))?
+        as usize;
+    let file_count = cursor
+        .read_u32::<LittleEndian>()
+        .map_err(|e| SnapshotError::new("header file count", e))? as usize;
+
+    let mut version_records = Vec::with_capacity(version_count);
+    for i in 0..version_count {
+        let context = format!("version record {i}");
+        version_records.push(RawVersionRecord {
+            name_offset: cursor
+                .read_u32::<LittleEndian>()
+                .map_err(|e| SnapshotError::new(context.as_str(), e))?,
+            name_len: cursor
+                .read_u32::<LittleEndian>()
+                .map_err(|e| SnapshotError::new(context.as_str(), e))?,
+            path_offset: cursor
+                .read_u32::<LittleEndian>()
+                .map_err(|e| SnapshotError::new(context.as_str(), e))?,
+            path_len: cursor
+                .read_u32::<LittleEndian>()
+                .map_err(|e| SnapshotError::new(context.as_str(), e))?,
+            file_start: cursor
+                .read_u32::<LittleEndian>()
+                .map_err(|e| SnapshotError::new(context.as_str(), e))?,
+            file_count: cursor
+                .read_u32::<LittleEndian>()
+                .map_err(|e| SnapshotError::new(context.as_str(), e))?,
+        });
+    }
+
+    let mut file_records = Vec::with_capacity(file_count);
+    for i in 0..file_count {
+        let context = format!("file record {i}");
+        let rel_path_offset = cursor
+            .read_u32::<LittleEndian>()
+            .map_err(|e| SnapshotError::new(context.as_str(), e))?;
+        let rel_path_len = cursor
+            .read_u32::<LittleEndian>()
+            .map_err(|e| SnapshotError::new(context.as_str(), e))?;
+        let has_digest = cursor
+            .read_u8()
+            .map_err(|e| SnapshotError::new(context.as_str(), e))?
+            != 0;
+        let mut digest = [0u8; 32];
+        cursor
+            .read_exact(&mut digest)
+            .map_err(|e| SnapshotError::new(context.as_str(), e))?;
+        let size = cursor
+            .read_u64::<LittleEndian>()
+            .map_err(|e| SnapshotError::new(context.as_str(), e))?;
+        let has_text = cursor
+            .read_u8()
+            .map_err(|e| SnapshotError::new(context.as_str(), e))?
+            != 0;
+        let text_offset = cursor
+            .read_u32::<LittleEndian>()
+            .map_err(|e| SnapshotError::new(context.as_str(), e))?;
+        let text_len = cursor
+            .read_u32::<LittleEndian>()
+            .map_err(|e| SnapshotError::new(context.as_str(), e))?;
+
+        file_records.push(RawFileRecord {
+            rel_path_offset,
+            rel_path_len,
+            digest: has_digest.then_some(digest),
+            size,
+            text_offset,
+            text_len,
+            has_text,
+        });
+    }
+
+    let blob_start = HEADER_LEN + version_count * VERSION_RECORD_LEN + file_count * FILE_RECORD_LEN;
+    let blob = buf.get(blob_start..).ok_or_else(|| {
+        SnapshotError::invalid(
+            "blob region",
+            format!("file is too short to contain the {blob_start}-byte record region"),
+        )
+    })?;
+
+    let mut versions = Vec::with_capacity(version_count);
+    for (i, record) in version_records.iter().enumerate() {
+        let context = format!("version record {i}");
+        let name = blob_str(
+            blob,
+            record.name_offset,
+            record.name_len,
+            &format!("{context} name"),
+        )?;
+        let path_str = blob_str(
+            blob,
+            record.path_offset,
+            record.path_len,
+            &format!("{context} path"),
+        )?;
+
+        let file_end = record.file_start as usize + record.file_count as usize;
+        let Some(records) = file_records.get(record.file_start as usize..file_end) else {
+            return Err(SnapshotError::invalid(
+                context,
+                format!(
+                    "file range {}..{file_end} is out of bounds ({} file records)",
+                    record.file_start,
+                    file_records.len()
+                ),
+            ));
+        };
+
+        let mut files = HashMap::with_capacity(records.len());
+        for (j, file_record) in records.iter().enumerate() {
+            let file_context = format!("{context} file {j}");
+            let rel_path = blob_str(
+                blob,
+                file_record.rel_path_offset,
+                file_record.rel_path_len,
+                &format!("{file_context} path"),
+            )?;
+            let text_content = if file_record.has_text {
+                Some(
+                    blob_str(
+                        blob,
+                        file_record.text_offset,
+                        file_record.text_len,
+                        &format!("{file_context} text"),
+                    )?
+                    .to_owned(),
+                )
+            } else {
+                None
+            };
+
+            files.insert(
+                rel_path.to_owned(),
+                FileData {
+                    text_content,
+                    digest: file_record.digest,
+                    size: file_record.size,
+                },
+            );
+        }
+
+        versions.push(Version {
+            name: name.to_owned(),
+            path: PathBuf::from(path_str).into(),
+            files,
+        });
+    }
+
+    Ok(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tempdir::TempDir;
+
+    use super::*;
+
+    fn version(name: &str, files: &[(&str, Option<&str>, Option<[u8; 32]>)]) -> Version {
+        Version {
+            name: name.to_owned(),
+            path: Path::new("/versions").join(name).into(),
+            files: files
+                .iter()
+                .map(|(rel_path, text, digest)| {
+                    (
+                        rel_path.to_string(),
+                        FileData {
+                            text_content: text.map(str::to_owned),
+                            digest: *digest,
+                            size: text.map(|t| t.len() as u64).unwrap_or(4),
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let tmp_dir = TempDir::new("test_temp").unwrap();
+        let path = tmp_dir.path().join("snapshot.bin");
+
+        let versions = vec![
+            version("version_1", &[("file_a.txt", Some("hello"), Some([1; 32]))]),
+            version(
+                "version_2",
+                &[
+                    ("file_a.txt", Some("hello world"), Some([2; 32])),
+                    ("image.png", None, Some([3; 32])),
+                ],
+            ),
+        ];
+
+        save_snapshot(&path, &versions).unwrap();
+        let loaded = load_snapshot(&path).unwrap();
+
+        assert_eq!(loaded.len(), 2);
+
+        let v1 = loaded.iter().find(|v| v.name == "version_1").unwrap();
+        assert_eq!(v1.path, Path::new("/versions/version_1").into());
+        assert_eq!(
+            v1.files["file_a.txt"].text_content.as_deref(),
+            Some("hello")
+        );
+        assert_eq!(v1.files["file_a.txt"].digest, Some([1; 32]));
+
+        let v2 = loaded.iter().find(|v| v.name == "version_2").unwrap();
+        assert_eq!(
+            v2.files["file_a.txt"].text_content.as_deref(),
+            Some("hello world")
+        );
+        assert_eq!(v2.files["image.png"].text_content, None);
+        assert_eq!(v2.files["image.png"].digest, Some([3; 32]));
+        assert_eq!(v2.files["image.png"].size, 4);
+
+        tmp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_load_rejects_wrong_magic() {
+        let tmp_dir = TempDir::new("test_temp").unwrap();
+        let path = tmp_dir.path().join("snapshot.bin");
+        fs::write(&path, b"not a snapshot").unwrap();
+
+        let err = load_snapshot(&path).unwrap_err();
+
+        assert_eq!(err.context, "header magic");
+
+        tmp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_load_rejects_truncated_blob_region() {
+        let tmp_dir = TempDir::new("test_temp").unwrap();
+        let path = tmp_dir.path().join("snapshot.bin");
+
+        save_snapshot(
+            &path,
+            &[version(
+                "version_1",
+                &[("file_a.txt", Some("hello"), Some([1; 32]))],
+            )],
+        )
+        .unwrap();
+
+        // Truncate the file partway through the blob region so the last blob
+        // reference (the file's inline text) points past the end of the data that's
+        // actually there
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.truncate(bytes.len() - 2);
+        fs::write(&path, &bytes).unwrap();
+
+        let err = load_snapshot(&path).unwrap_err();
+
+        assert!(err.context.contains("version record 0"));
+
+        tmp_dir.close().unwrap();
+    }
+}
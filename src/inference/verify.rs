@@ -0,0 +1,162 @@
+use std::collections::{HashMap, HashSet};
+
+use indicatif::MultiProgress;
+
+use crate::{
+    inference::{
+        distance_model::DistanceModel, edmonds::collect_descendants,
+        engine::compute_divergence_matrix,
+    },
+    types::{DiffInfo, TreeNode, Version},
+};
+
+/// A node has a cheaper available parent in the current directory than the one
+/// `version_tree.json` recorded, meaning the tree is no longer an optimal arborescence
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheaperParentViolation {
+    pub version: String,
+    pub recorded_parent: String,
+    pub recorded_cost: f32,
+    pub better_parent: String,
+    pub better_cost: f32,
+}
+
+/// Result of checking a previously-inferred tree against the current state of its
+/// source directory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerifyReport {
+    /// Versions present in the tree but no longer found in the directory
+    pub stale_versions: Vec<String>,
+    /// Versions found in the directory but missing from the tree
+    pub new_versions: Vec<String>,
+    /// Names that appear more than once in the tree, so it isn't a single arborescence
+    pub duplicate_nodes: Vec<String>,
+    pub cheaper_parent_violations: Vec<CheaperParentViolation>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.stale_versions.is_empty()
+            && self.new_versions.is_empty()
+            && self.duplicate_nodes.is_empty()
+            && self.cheaper_parent_violations.is_empty()
+    }
+}
+
+/// Flatten a tree into `(name, parent_name)` pairs; the root's parent is `None`
+fn collect_edges(
+    node: &TreeNode<DiffInfo>,
+    parent: Option<&str>,
+    edges: &mut Vec<(String, Option<String>)>,
+) {
+    edges.push((node.value.name.to_owned(), parent.map(str::to_owned)));
+    for child in &node.children {
+        collect_edges(child, Some(&node.value.name), edges);
+    }
+}
+
+/// Check that `version_tree` still matches `versions`: every node maps to a version
+/// that still exists, the tree is a single arborescence (no name appears twice), and
+/// no node has a strictly cheaper available parent than the one recorded, by
+/// recomputing the divergence matrix the same way `infer_version_tree` does. `model`
+/// and `rename_threshold` must match whatever was used to infer `version_tree` in the
+/// first place, or the recomputed costs won't be comparable to the recorded ones
+pub fn verify_tree(
+    version_tree: &TreeNode<DiffInfo>,
+    versions: Vec<Version>,
+    multithreading: bool,
+    mp: &MultiProgress,
+    model: &dyn DistanceModel,
+    rename_threshold: f32,
+) -> VerifyReport {
+    let mut report = VerifyReport::default();
+
+    let mut edges = vec![];
+    collect_edges(version_tree, None, &mut edges);
+
+    let mut seen = HashSet::new();
+    for (name, _) in &edges {
+        if !seen.insert(name.as_str()) {
+            report.duplicate_nodes.push(name.to_owned());
+        }
+    }
+
+    let tree_names: HashSet<&str> = edges.iter().map(|(name, _)| name.as_str()).collect();
+    let disk_names: HashSet<&str> = versions.iter().map(|v| v.name.as_str()).collect();
+
+    report.stale_versions = tree_names
+        .difference(&disk_names)
+        .filter(|name| **name != "Empty")
+        .map(|name| name.to_string())
+        .collect();
+    report.new_versions = disk_names
+        .difference(&tree_names)
+        .map(|name| name.to_string())
+        .collect();
+
+    let (recomputed_versions, _duplicates_of, divergences, _div_calc_res) =
+        compute_divergence_matrix(versions, multithreading, mp, None, model, rename_threshold);
+
+    let name_to_idx: HashMap<&str, usize> = recomputed_versions
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (v.name.as_str(), i))
+        .collect();
+
+    // Children adjacency over the recorded tree (restricted to versions that still
+    // have a stable row in the recomputed matrix), used to exclude a node's own
+    // descendants from its candidate-parent search below
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); recomputed_versions.len()];
+    for (name, parent) in &edges {
+        let Some(parent) = parent else { continue };
+        if let (Some(&child_idx), Some(&parent_idx)) = (
+            name_to_idx.get(name.as_str()),
+            name_to_idx.get(parent.as_str()),
+        ) {
+            children[parent_idx].push(child_idx);
+        }
+    }
+
+    for (name, parent) in &edges {
+        let Some(parent) = parent else {
+            continue; // the root has no incoming edge to check
+        };
+        // Versions already flagged as stale/new/duplicate don't have a stable row in
+        // the recomputed matrix, so they're skipped here rather than double-reported
+        let (Some(&to), Some(&from)) = (
+            name_to_idx.get(name.as_str()),
+            name_to_idx.get(parent.as_str()),
+        ) else {
+            continue;
+        };
+
+        let recorded_cost = divergences[(from, to)];
+
+        // A candidate parent that is `to` itself or one of its descendants would
+        // form a cycle, so it's excluded here the same way
+        // `edmonds::find_msa_with_margins` excludes them from its margin search
+        let mut excluded = HashSet::from([to]);
+        collect_descendants(&children, to, &mut excluded);
+
+        let cheapest_other = (0..recomputed_versions.len())
+            .filter(|candidate| *candidate != from && !excluded.contains(candidate))
+            .map(|candidate| (candidate, divergences[(candidate, to)]))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        if let Some((candidate, candidate_cost)) = cheapest_other {
+            if candidate_cost < recorded_cost {
+                report
+                    .cheaper_parent_violations
+                    .push(CheaperParentViolation {
+                        version: name.clone(),
+                        recorded_parent: parent.clone(),
+                        recorded_cost,
+                        better_parent: recomputed_versions[candidate].name.to_owned(),
+                        better_cost: candidate_cost,
+                    });
+            }
+        }
+    }
+
+    report
+}
@@ -0,0 +1,205 @@
+use std::cmp::Ordering;
+
+/// A parsed ordering hint extracted from a version's folder/file name, used to bias
+/// edge costs in `infer_version_tree` towards the direction real releases usually
+/// flow in. Names that don't fit either scheme are `Opaque` and get no bias
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionLabel {
+    /// `[v]major.minor.patch[-prerelease]`
+    SemVer {
+        major: u64,
+        minor: u64,
+        patch: u64,
+        pre: Option<String>,
+    },
+    /// A bare numeric or date-like name (e.g. `2`, `2023-05-01`), ordered as the
+    /// integer left after stripping non-digit separators
+    Rapid(u64),
+    Opaque,
+}
+
+pub fn parse_version_label(name: &str) -> VersionLabel {
+    parse_semver(name)
+        .or_else(|| parse_rapid(name).map(VersionLabel::Rapid))
+        .unwrap_or(VersionLabel::Opaque)
+}
+
+fn parse_semver(name: &str) -> Option<VersionLabel> {
+    let trimmed = name.strip_prefix('v').unwrap_or(name);
+    let (core, pre) = match trimmed.split_once('-') {
+        Some((core, pre)) => (core, Some(pre.to_owned())),
+        None => (trimmed, None),
+    };
+
+    let mut parts = core.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    let patch = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(VersionLabel::SemVer {
+        major,
+        minor,
+        patch,
+        pre,
+    })
+}
+
+fn parse_rapid(name: &str) -> Option<u64> {
+    let trimmed = name.strip_prefix('v').unwrap_or(name);
+    if trimmed.is_empty()
+        || !trimmed
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == '-' || c == '_')
+    {
+        return None;
+    }
+
+    let digits: String = trimmed.chars().filter(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// Whether `to` is an immediate SemVer successor of `from`: the same major.minor with
+/// patch+1, or a prerelease collapsing to its release (same major.minor.patch)
+pub fn is_semver_successor(from: &VersionLabel, to: &VersionLabel) -> bool {
+    let (
+        VersionLabel::SemVer {
+            major: from_major,
+            minor: from_minor,
+            patch: from_patch,
+            pre: from_pre,
+        },
+        VersionLabel::SemVer {
+            major: to_major,
+            minor: to_minor,
+            patch: to_patch,
+            pre: to_pre,
+        },
+    ) = (from, to)
+    else {
+        return false;
+    };
+
+    if from_major != to_major || from_minor != to_minor {
+        return false;
+    }
+
+    match (from_pre, to_pre) {
+        (Some(_), None) => from_patch == to_patch,
+        (None, None) => *to_patch == from_patch + 1,
+        _ => false,
+    }
+}
+
+/// Compares two labels' ordering, if they're parsed with the same scheme; labels
+/// parsed with different schemes (or `Opaque`) have no meaningful relative order
+pub fn compare_order(from: &VersionLabel, to: &VersionLabel) -> Option<Ordering> {
+    match (from, to) {
+        (
+            VersionLabel::SemVer {
+                major: from_major,
+                minor: from_minor,
+                patch: from_patch,
+                ..
+            },
+            VersionLabel::SemVer {
+                major: to_major,
+                minor: to_minor,
+                patch: to_patch,
+                ..
+            },
+        ) => Some((from_major, from_minor, from_patch).cmp(&(to_major, to_minor, to_patch))),
+        (VersionLabel::Rapid(from), VersionLabel::Rapid(to)) => Some(from.cmp(to)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_semver() {
+        assert_eq!(
+            parse_version_label("v1.3.0"),
+            VersionLabel::SemVer {
+                major: 1,
+                minor: 3,
+                patch: 0,
+                pre: None,
+            }
+        );
+        assert_eq!(
+            parse_version_label("2.0.1-rc1"),
+            VersionLabel::SemVer {
+                major: 2,
+                minor: 0,
+                patch: 1,
+                pre: Some("rc1".to_owned()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_rapid() {
+        assert_eq!(parse_version_label("2023-05-01"), VersionLabel::Rapid(20230501));
+        assert_eq!(parse_version_label("version_2"), VersionLabel::Opaque);
+        assert_eq!(parse_version_label("v7"), VersionLabel::Rapid(7));
+    }
+
+    #[test]
+    fn test_parse_opaque() {
+        assert_eq!(parse_version_label("main"), VersionLabel::Opaque);
+        assert_eq!(parse_version_label("feature-branch"), VersionLabel::Opaque);
+    }
+
+    #[test]
+    fn test_is_semver_successor() {
+        let v1_2_3 = VersionLabel::SemVer {
+            major: 1,
+            minor: 2,
+            patch: 3,
+            pre: None,
+        };
+        let v1_2_4 = VersionLabel::SemVer {
+            major: 1,
+            minor: 2,
+            patch: 4,
+            pre: None,
+        };
+        let v1_2_4_rc = VersionLabel::SemVer {
+            major: 1,
+            minor: 2,
+            patch: 4,
+            pre: Some("rc1".to_owned()),
+        };
+
+        assert!(is_semver_successor(&v1_2_3, &v1_2_4));
+        assert!(is_semver_successor(&v1_2_4_rc, &v1_2_4));
+        assert!(!is_semver_successor(&v1_2_4, &v1_2_3));
+    }
+
+    #[test]
+    fn test_compare_order() {
+        let v1 = VersionLabel::SemVer {
+            major: 1,
+            minor: 0,
+            patch: 0,
+            pre: None,
+        };
+        let v2 = VersionLabel::SemVer {
+            major: 2,
+            minor: 0,
+            patch: 0,
+            pre: None,
+        };
+
+        assert_eq!(compare_order(&v1, &v2), Some(Ordering::Less));
+        assert_eq!(compare_order(&v2, &v1), Some(Ordering::Greater));
+        assert_eq!(compare_order(&v1, &VersionLabel::Opaque), None);
+    }
+}
@@ -1,10 +1,102 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use octocrab::{models::Repository, params::repos::forks::Sort, Octocrab};
 use serde::{Deserialize, Serialize};
 
 use crate::types::TreeNode;
 
+/// Shallow map from a commit SHA to the SHAs of its parents, as returned by the
+/// `GET /commits` endpoint, used as an offline substitute for the `compare` API
+/// when computing fork points
+type CommitGraph = HashMap<String, Vec<String>>;
+
+/// Every commit reachable from `start` (inclusive), following all parent edges
+fn ancestors(graph: &CommitGraph, start: &str) -> HashSet<String> {
+    let mut seen = HashSet::new();
+    let mut stack = vec![start.to_owned()];
+
+    while let Some(sha) = stack.pop() {
+        if !seen.insert(sha.clone()) {
+            continue;
+        }
+        if let Some(parents) = graph.get(&sha) {
+            stack.extend(parents.iter().cloned());
+        }
+    }
+
+    seen
+}
+
+/// `start` and each commit's first parent, back until a commit with no recorded
+/// parent (usually because the shallow fetch didn't go back that far)
+fn first_parent_chain(graph: &CommitGraph, start: &str) -> Vec<String> {
+    let mut chain = vec![start.to_owned()];
+
+    while let Some(parent) = graph
+        .get(chain.last().unwrap())
+        .and_then(|parents| parents.first())
+    {
+        chain.push(parent.clone());
+    }
+
+    chain
+}
+
+/// Find the fork point the way `git merge-base` would: the first commit in the
+/// child's first-parent history (walking back from HEAD) that is also an ancestor
+/// of the parent's HEAD. `behind_by` is then the number of parent-HEAD ancestors
+/// that aren't also reachable from that merge base, mirroring the GitHub compare
+/// API's `behind_by`/`merge_base_commit` pair
+fn find_fork_point_offline(
+    parent_graph: &CommitGraph,
+    parent_head: &str,
+    child_graph: &CommitGraph,
+    child_head: &str,
+) -> Option<(u64, String)> {
+    let parent_ancestors = ancestors(parent_graph, parent_head);
+
+    let merge_base = first_parent_chain(child_graph, child_head)
+        .into_iter()
+        .find(|sha| parent_ancestors.contains(sha))?;
+
+    let merge_base_ancestors = ancestors(parent_graph, &merge_base);
+    let behind_by = parent_ancestors.difference(&merge_base_ancestors).count() as u64;
+
+    Some((behind_by, merge_base))
+}
+
+/// Fetch a shallow commit graph for `sha` and its ancestors, up to one page, for use
+/// as an offline fallback when the compare API is unavailable or rate-limited.
+/// Returns the graph alongside the resolved SHA of `sha` itself, since `sha` may be
+/// a ref name like "HEAD" rather than the commit's actual hash
+async fn fetch_commit_graph(
+    octo: &Octocrab,
+    owner: &str,
+    repo: &str,
+    sha: &str,
+) -> Option<(CommitGraph, String)> {
+    let commits = octo
+        .repos(owner, repo)
+        .list_commits()
+        .sha(sha)
+        .per_page(100)
+        .send()
+        .await
+        .ok()?;
+
+    let mut items = commits.items.into_iter().peekable();
+    let head_sha = items.peek()?.sha.clone();
+
+    let graph = items
+        .map(|commit| {
+            let parents = commit.parents.into_iter().map(|p| p.sha).collect();
+            (commit.sha, parents)
+        })
+        .collect();
+
+    Some((graph, head_sha))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VersionRef {
     pub owner: String,
@@ -45,18 +137,32 @@ async fn find_fork_point(
         .await
         .ok();
 
-    let comparison = match comparison {
-        Some(c) => c,
-        None => {
-            println!("WARNING: Discarding {child_owner}/{parent_repo} (failed to find fork point)");
-            return None;
+    if let Some(comparison) = comparison {
+        let behind_by = comparison.behind_by;
+        assert!(behind_by >= 0);
+
+        return Some((behind_by as u64, comparison.merge_base_commit.sha));
+    }
+
+    println!(
+        "Compare API failed for {child_owner}/{parent_repo}, falling back to an offline merge-base search"
+    );
+
+    let parent_graph = fetch_commit_graph(octo, parent_owner, parent_repo, "HEAD").await;
+    let child_graph = fetch_commit_graph(octo, child_owner, parent_repo, "HEAD").await;
+
+    let fork_point = match (parent_graph, child_graph) {
+        (Some((parent_graph, parent_head)), Some((child_graph, child_head))) => {
+            find_fork_point_offline(&parent_graph, &parent_head, &child_graph, &child_head)
         }
+        _ => None,
     };
 
-    let behind_by = comparison.behind_by;
-    assert!(behind_by >= 0);
+    if fork_point.is_none() {
+        println!("WARNING: Discarding {child_owner}/{parent_repo} (failed to find fork point)");
+    }
 
-    Some((behind_by as u64, comparison.merge_base_commit.sha))
+    fork_point
 }
 
 async fn get_head_commit(octo: &Octocrab, owner: &str, repo: &str) -> Option<String> {
@@ -1,9 +1,16 @@
-use std::{collections::HashMap, fmt, path::Path};
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+    fmt,
+    hash::Hash,
+    ops::AddAssign,
+    path::Path,
+};
 
 use serde::{Deserialize, Serialize};
 use similar::ChangeTag;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TextChange {
     pub tag: ChangeTag,
     pub old_index: Option<usize>,
@@ -11,10 +18,41 @@ pub struct TextChange {
     pub value: String,
 }
 
+/// Byte-size delta for a file that has no `text_content` on at least one side, so
+/// an opaque change (binary, or a text/binary transition) still carries a usable
+/// size/hash-backed signal instead of an empty line diff
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinarySizes {
+    pub old_size: Option<u64>,
+    pub new_size: Option<u64>,
+}
+
 #[derive(Debug)]
 pub struct FileChange {
     pub filename: String,
     pub changes: Vec<TextChange>,
+    /// `Some` when this file (or one side of it) is binary, in which case `changes`
+    /// is always empty: a line diff can't say anything meaningful about it
+    pub binary_sizes: Option<BinarySizes>,
+}
+
+/// A unified-diff-style hunk: a run of changed lines plus up to `context` lines of
+/// surrounding unchanged context on each side. `old_start`/`new_start` are 1-indexed
+/// line numbers, matching a `@@ -old_start,old_len +new_start,new_len @@` header
+#[derive(Debug)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<TextChange>,
+}
+
+#[derive(Debug)]
+pub struct RenamedFile {
+    pub old_name: String,
+    pub new_name: String,
+    pub changes: Vec<TextChange>,
 }
 
 #[derive(Debug)]
@@ -22,11 +60,49 @@ pub struct TextualVersionDiff {
     pub added_files: Vec<FileChange>,
     pub deleted_files: Vec<FileChange>,
     pub modified_files: Vec<FileChange>,
+    pub renamed_files: Vec<RenamedFile>,
+}
+
+/// Forward/backward pair of accumulated edge costs between two versions
+#[derive(Debug, Clone, Copy)]
+pub struct Pair(pub f32, pub f32);
+
+impl AddAssign for Pair {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+        self.1 += rhs.1;
+    }
+}
+
+/// File-level summary of a divergence calculation in one direction
+#[derive(Debug, Clone, Copy)]
+pub struct DivCalcResult {
+    pub added: usize,
+    pub deleted: usize,
+    pub modified: usize,
+    pub divergence: f32,
+}
+
+impl DivCalcResult {
+    pub fn new() -> Self {
+        Self {
+            added: 0,
+            deleted: 0,
+            modified: 0,
+            divergence: 0.,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct FileData {
     pub text_content: Option<String>,
+    /// Content digest, populated regardless of whether the file is valid UTF-8
+    pub digest: Option<[u8; 32]>,
+    /// Byte length of the file, populated regardless of whether the file is valid
+    /// UTF-8, so binary files still carry a notion of size (reported through
+    /// `BinarySizes` when diffing, since there is no `text_content` to diff)
+    pub size: u64,
 }
 
 #[derive(Debug)]
@@ -36,6 +112,27 @@ pub struct Version {
     pub files: HashMap<String, FileData>,
 }
 
+impl Version {
+    /// Content-addressed digest of this version as a whole: a hash over the
+    /// sorted `(filename, file_digest)` pairs, so two versions with identical
+    /// files hash identically regardless of file ordering
+    pub fn merkle_digest(&self) -> [u8; 32] {
+        let mut entries: Vec<(&String, Option<[u8; 32]>)> = self
+            .files
+            .iter()
+            .map(|(name, data)| (name, data.digest))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut hasher = blake3::Hasher::new();
+        for (name, digest) in entries {
+            hasher.update(name.as_bytes());
+            hasher.update(&digest.unwrap_or([0; 32]));
+        }
+        *hasher.finalize().as_bytes()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TreeNode<T> {
     pub value: T,
@@ -48,6 +145,18 @@ pub struct DiffInfo {
     pub added: usize,
     pub deleted: usize,
     pub modified: usize,
+    pub divergence: f32,
+    /// How much cheaper the chosen parent was than the best non-cycle-forming
+    /// alternative; `None` for the root or when no valid alternative existed. A value
+    /// near zero flags a branch point that was close to going the other way
+    pub margin: Option<f32>,
+}
+
+impl DiffInfo {
+    /// Whether this node's edge from its parent represents no detected changes at all
+    pub fn no_changes(&self) -> bool {
+        self.added == 0 && self.deleted == 0 && self.modified == 0
+    }
 }
 
 impl<T: Eq> PartialEq for TreeNode<T> {
@@ -91,6 +200,147 @@ impl<T> TreeNode<T> {
     {
         self.map_with_parent(&|x, _| f(x), None)
     }
+
+    /// Depth-first traversal yielding `(depth, &node)`, root first at depth 0
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            stack: vec![(0, self)],
+        }
+    }
+
+    /// Nodes strictly below the one whose value equals `value`, in DFS order with
+    /// depth counted from that node; `None` if no node in this tree has `value`
+    pub fn descendants(&self, value: &T) -> Option<Iter<'_, T>>
+    where
+        T: Eq,
+    {
+        let (_, node) = self.iter().find(|(_, n)| &n.value == value)?;
+        Some(Iter {
+            stack: node.children.iter().map(|c| (1, c)).collect(),
+        })
+    }
+
+    /// Lazy, nearest-first walk up from the node whose value equals `value`,
+    /// yielding each ancestor in turn without materializing the full set.
+    /// `max_depth`, if given, is the shallowest tree depth the walk is allowed to
+    /// reach; ancestors above that depth are never yielded. `None` if no node in
+    /// this tree has `value`
+    pub fn ancestors(&self, value: &T, max_depth: Option<usize>) -> Option<Ancestors<T>>
+    where
+        T: Clone + Eq + Hash,
+    {
+        let mut parents = HashMap::new();
+        let mut stack = vec![(0usize, None::<T>, self)];
+        while let Some((depth, parent, node)) = stack.pop() {
+            parents.insert(node.value.clone(), ParentInfo { parent, depth });
+            for child in &node.children {
+                stack.push((depth + 1, Some(node.value.clone()), child));
+            }
+        }
+
+        let depth = parents.get(value)?.depth;
+        let mut heap = BinaryHeap::new();
+        heap.push(HeapEntry {
+            depth,
+            value: value.clone(),
+        });
+        let mut seen = HashSet::new();
+        seen.insert(value.clone());
+
+        Some(Ancestors {
+            parents,
+            heap,
+            seen,
+            max_depth,
+        })
+    }
+}
+
+/// Depth-first iterator over a `TreeNode`, yielding `(depth, &node)`. See
+/// `TreeNode::iter`/`TreeNode::descendants`
+pub struct Iter<'a, T> {
+    stack: Vec<(usize, &'a TreeNode<T>)>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (usize, &'a TreeNode<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, node) = self.stack.pop()?;
+        self.stack
+            .extend(node.children.iter().map(|c| (depth + 1, c)));
+        Some((depth, node))
+    }
+}
+
+struct ParentInfo<T> {
+    parent: Option<T>,
+    depth: usize,
+}
+
+struct HeapEntry<T> {
+    depth: usize,
+    value: T,
+}
+
+impl<T> PartialEq for HeapEntry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.depth == other.depth
+    }
+}
+
+impl<T> Eq for HeapEntry<T> {}
+
+impl<T> PartialOrd for HeapEntry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for HeapEntry<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Deepest first, so the frontier always advances from its lowest node
+        self.depth.cmp(&other.depth)
+    }
+}
+
+/// Lazy ancestor walk produced by `TreeNode::ancestors`. Mirrors Mercurial's
+/// `AncestorsIterator`: a max-heap of the current frontier keeps the deepest
+/// unexplored node on top, so each `next()` call does only the work needed to
+/// surface one more ancestor rather than rebuilding the whole ancestor set
+pub struct Ancestors<T> {
+    parents: HashMap<T, ParentInfo<T>>,
+    heap: BinaryHeap<HeapEntry<T>>,
+    seen: HashSet<T>,
+    max_depth: Option<usize>,
+}
+
+impl<T: Clone + Eq + Hash> Iterator for Ancestors<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            let HeapEntry { depth, value } = self.heap.pop()?;
+            let parent = self.parents.get(&value)?.parent.clone()?;
+            let parent_depth = depth - 1;
+
+            if let Some(max_depth) = self.max_depth {
+                if parent_depth < max_depth {
+                    continue;
+                }
+            }
+
+            if !self.seen.insert(parent.clone()) {
+                // Already walked through this ancestor via another branch
+                continue;
+            }
+            self.heap.push(HeapEntry {
+                depth: parent_depth,
+                value: parent.clone(),
+            });
+            return Some(parent);
+        }
+    }
 }
 
 impl render_as_tree::Node for TreeNode<String> {
@@ -119,3 +369,99 @@ impl fmt::Display for TextChange {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn node(value: &str, children: Vec<TreeNode<String>>) -> TreeNode<String> {
+        TreeNode {
+            value: value.to_owned(),
+            children,
+        }
+    }
+
+    fn sample_tree() -> TreeNode<String> {
+        // root -> a -> (b, c) ; c -> d
+        node(
+            "root",
+            vec![node(
+                "a",
+                vec![node("b", vec![]), node("c", vec![node("d", vec![])])],
+            )],
+        )
+    }
+
+    #[test]
+    fn test_iter_visits_every_node_with_correct_depth() {
+        let tree = sample_tree();
+
+        let mut visited: Vec<(usize, &str)> = tree
+            .iter()
+            .map(|(depth, node)| (depth, node.value.as_str()))
+            .collect();
+        visited.sort();
+
+        assert_eq!(
+            visited,
+            vec![(0, "root"), (1, "a"), (2, "b"), (2, "c"), (3, "d"),]
+        );
+    }
+
+    #[test]
+    fn test_descendants_of_unknown_value_is_none() {
+        let tree = sample_tree();
+        assert!(tree.descendants(&"missing".to_owned()).is_none());
+    }
+
+    #[test]
+    fn test_descendants_excludes_queried_node_and_siblings() {
+        let tree = sample_tree();
+
+        let mut names: Vec<&str> = tree
+            .descendants(&"a".to_owned())
+            .unwrap()
+            .map(|(_, node)| node.value.as_str())
+            .collect();
+        names.sort();
+
+        assert_eq!(names, vec!["b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_ancestors_walks_from_nearest_to_root() {
+        let tree = sample_tree();
+
+        let ancestors: Vec<String> = tree.ancestors(&"d".to_owned(), None).unwrap().collect();
+
+        assert_eq!(
+            ancestors,
+            vec!["c".to_owned(), "a".to_owned(), "root".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_ancestors_respects_depth_cutoff() {
+        let tree = sample_tree();
+
+        // "root" is at depth 0, "a" at depth 1: stopping at depth 1 should exclude root
+        let ancestors: Vec<String> = tree.ancestors(&"d".to_owned(), Some(1)).unwrap().collect();
+
+        assert_eq!(ancestors, vec!["c".to_owned(), "a".to_owned()]);
+    }
+
+    #[test]
+    fn test_ancestors_of_root_is_empty() {
+        let tree = sample_tree();
+        let ancestors: Vec<String> = tree.ancestors(&"root".to_owned(), None).unwrap().collect();
+        assert!(ancestors.is_empty());
+    }
+
+    #[test]
+    fn test_ancestors_of_unknown_value_is_none() {
+        let tree = sample_tree();
+        assert!(tree.ancestors(&"missing".to_owned(), None).is_none());
+    }
+}
@@ -8,7 +8,7 @@ use std::{
     time::{Duration, Instant},
 };
 
-use crate::types::Version;
+use crate::types::{DiffInfo, TreeNode, Version};
 
 pub static PB_BAR_STYLE: LazyLock<ProgressStyle> = LazyLock::new(|| {
     ProgressStyle::with_template("[{elapsed_precise}] {prefix:20} {bar:60} {pos:>7}/{len:7} {msg}")
@@ -83,3 +83,64 @@ impl InferencePerformanceTracker {
         self.started.elapsed()
     }
 }
+
+/// Heuristic for whether `bytes` should be treated as binary rather than text,
+/// mirroring git's own check: a NUL byte is a near-certain sign of binary content,
+/// even for bytes that otherwise happen to decode as valid UTF-8
+pub fn is_probably_binary(bytes: &[u8]) -> bool {
+    bytes.contains(&0)
+}
+
+pub fn produce_label_tree(diff_tree: &TreeNode<DiffInfo>) -> TreeNode<String> {
+    fn f(d: &DiffInfo) -> String {
+        match d.margin {
+            Some(margin) => format!(
+                "{} - FILES: {} A, {} D, {} M - MARGIN: {:.2}",
+                d.name, d.added, d.deleted, d.modified, margin,
+            ),
+            None => format!(
+                "{} - FILES: {} A, {} D, {} M",
+                d.name, d.added, d.deleted, d.modified,
+            ),
+        }
+    }
+    diff_tree.map(&f)
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Render a `TreeNode<DiffInfo>` as a Graphviz digraph, with one node per version and
+/// edges labeled by the diff summary stored on the child, so it can be piped into
+/// e.g. `dot -Tsvg` for a visual version graph
+pub fn produce_dot(diff_tree: &TreeNode<DiffInfo>) -> String {
+    fn walk(node: &TreeNode<DiffInfo>, lines: &mut Vec<String>) {
+        lines.push(format!("    \"{}\";", dot_escape(&node.value.name)));
+
+        for child in &node.children {
+            let label = match child.value.margin {
+                Some(margin) => format!(
+                    "{} A, {} D, {} M, margin {:.2}",
+                    child.value.added, child.value.deleted, child.value.modified, margin,
+                ),
+                None => format!(
+                    "{} A, {} D, {} M",
+                    child.value.added, child.value.deleted, child.value.modified,
+                ),
+            };
+            lines.push(format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                dot_escape(&node.value.name),
+                dot_escape(&child.value.name),
+                dot_escape(&label),
+            ));
+            walk(child, lines);
+        }
+    }
+
+    let mut lines = vec!["digraph version_tree {".to_string()];
+    walk(diff_tree, &mut lines);
+    lines.push("}".to_string());
+    lines.join("\n")
+}